@@ -0,0 +1,234 @@
+use zed::serde_json::{json, to_string_pretty};
+use zed_extension_api as zed;
+
+use crate::settings::{LOG_LEVELS, TOOL_CATEGORIES};
+
+const INSTALLATION_INSTRUCTIONS: &str = r#"# Notepad++ MCP Server
+
+The extension starts the `notepadpp-mcp` Python package, which must be installed first:
+
+```sh
+pip install notepadpp-mcp
+```
+
+With [uv](https://docs.astral.sh/uv/) on your `PATH` no manual install is needed; the server is run through `uv run`.
+
+Notepad++ itself must be installed on the machine the server runs on.
+
+Settings shared by a team can be committed as `.notepadpp-mcp.toml` in the project root. Zed does not tell extensions where the project is, so that file and the project's `.env` are only read when `working_directory` is set to the project's path. It may set `package_version`, `log_level`, `notepad_path`, and `enabled_tools`; the Zed settings below take precedence.
+
+To start the server with a command of your own, add a `command` block next to `settings`. It replaces the detected launcher, the automatic install, and the environment the extension builds:
+
+```json
+"context_servers": {
+  "notepadpp-mcp": {
+    "command": {
+      "path": "uv",
+      "arguments": ["run", "notepadpp_mcp.tools.server:run"],
+      "env": {}
+    }
+  }
+}
+```
+"#;
+
+/// Describes the settings UI Zed renders for the `notepadpp-mcp` context server.
+pub fn context_server_configuration() -> zed::Result<zed::ContextServerConfiguration> {
+    let default_settings = json!({
+        "log_level": "INFO"
+    });
+
+    let settings_schema = json!({
+        "type": "object",
+        "properties": {
+            "disabled": {
+                "type": "boolean",
+                "default": false,
+                "description": "Stop the server from launching without uninstalling the extension."
+            },
+            "notepad_path": {
+                "type": "string",
+                "description": "Absolute path to notepad++.exe. Detected automatically when omitted."
+            },
+            "log_level": {
+                "type": "string",
+                "enum": LOG_LEVELS,
+                "default": "INFO",
+                "description": "Log level of the MCP server."
+            },
+            "appearance": {
+                "type": "string",
+                "enum": ["dark", "light", "auto"],
+                "default": "auto",
+                "description": "Theme mode Notepad++ is asked to use. Zed does not share its own appearance with extensions, so set this to match it."
+            },
+            "package_version": {
+                "type": "string",
+                "description": "Exact notepadpp-mcp package version to run, e.g. \"0.1.0\"."
+            },
+            "wine_prefix": {
+                "type": "string",
+                "description": "Wine prefix containing Notepad++. Required on macOS and Linux."
+            },
+            "auto_install": {
+                "type": "boolean",
+                "default": true,
+                "description": "Install the notepadpp-mcp package on launch when it is missing."
+            },
+            "offline": {
+                "type": "boolean",
+                "default": false,
+                "description": "Never access the network: uv runs with --offline and nothing is downloaded or installed. The package must already be installed."
+            },
+            "install_check_ttl_secs": {
+                "type": "integer",
+                "minimum": 0,
+                "default": 300,
+                "description": "Seconds the result of checking whether the package is installed is reused before checking again."
+            },
+            "install_timeout_secs": {
+                "type": "integer",
+                "minimum": 1,
+                "default": 120,
+                "description": "Network timeout in seconds for automatic installs, passed to uv and pip as UV_HTTP_TIMEOUT and PIP_TIMEOUT. This is not a deadline for the install as a whole: Zed gives extensions no way to stop a running command, so an install that hangs for another reason still blocks the launch."
+            },
+            "probe_on_launch": {
+                "type": "boolean",
+                "default": false,
+                "description": "Check that the server answers the MCP handshake (or the http server's /api/health endpoint) before starting it. Spawns the server an extra time."
+            },
+            "with_packages": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Extra Python requirements, e.g. \"requests>=2.31\", passed to `uv run` or `uvx` as `--with` flags for server plugins. Not supported when launching with `python_path` or a bare Python."
+            },
+            "trace": {
+                "type": "boolean",
+                "default": false,
+                "description": "Run the server under `py-spy record` to diagnose hangs. Ignored, with a note in NOTEPADPP_TRACE_UNAVAILABLE, when py-spy is not on PATH."
+            },
+            "trace_output": {
+                "type": "string",
+                "description": "File the trace is written to. Defaults to notepadpp-mcp-trace.svg in working_directory, which must then be set."
+            },
+            "python_mode": {
+                "type": "string",
+                "enum": ["managed", "system"],
+                "default": "managed",
+                "description": "\"managed\" lets uv pick or download a Python for an isolated environment, which is reproducible but ignores your active interpreter. \"system\" prefers the Pythons already installed, so packages and settings there apply, at the cost of depending on what the machine has."
+            },
+            "ssh_target": {
+                "type": "string",
+                "pattern": "^[A-Za-z0-9._][A-Za-z0-9._-]*@[A-Za-z0-9.][A-Za-z0-9.-]*$",
+                "description": "Run the server on a remote Windows machine over SSH, as `user@host`. uv must be on the remote PATH, `notepad_path` refers to the remote filesystem, and the remote sshd must accept the forwarded NOTEPADPP_* and UV_* variables (AcceptEnv). Other variables, such as proxies and .env secrets, are not forwarded."
+            },
+            "lock_file": {
+                "type": "string",
+                "description": "Lock file removed by /notepad-restart after a crash. Defaults to notepadpp-mcp.lock in working_directory, which must then be set; relative paths also resolve against it; only files named like notepadpp-mcp*.lock or notepadpp-mcp*.pid are removed."
+            },
+            "args_template": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Arguments replacing the default ones passed to the detected launcher. ${project_root}, ${notepad_path}, ${session_id}, and ${package_version} are substituted; other ${...} tokens are an error."
+            },
+            "allowed_roots": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Directories the server may access, forwarded as NOTEPADPP_ALLOWED_ROOTS. Relative entries resolve against working_directory; each must exist and lie inside it unless allow_external_roots is set. Without working_directory, only absolute entries with allow_external_roots are accepted. Unrestricted when empty."
+            },
+            "allow_external_roots": {
+                "type": "boolean",
+                "default": false,
+                "description": "Allow allowed_roots entries outside the project, e.g. \"../shared\"."
+            },
+            "python_path": {
+                "type": "string",
+                "description": "Python interpreter that runs the server directly instead of uv."
+            },
+            "dev_repo_path": {
+                "type": "string",
+                "description": "Local notepadpp-mcp checkout run by the notepadpp-mcp-dev server."
+            },
+            "working_directory": {
+                "type": "string",
+                "description": "Absolute directory the server is started in; relative session files resolve against it. Zed does not tell extensions where the project is, so set this to the project's path to read its .env and .notepadpp-mcp.toml, substitute ${project_root}, and resolve relative python_path, notepad_path, lock_file, and allowed_roots. \"project_root\" requires an open folder but does not provide its path."
+            },
+            "transport": {
+                "type": "string",
+                "enum": ["stdio", "http"],
+                "default": "stdio",
+                "description": "Spawn the server over stdio, or connect to a running server at `url`."
+            },
+            "url": {
+                "type": "string",
+                "format": "uri",
+                "description": "MCP endpoint of a running server, e.g. \"http://127.0.0.1:10815/mcp\". Required for the http transport."
+            },
+            "release_tag": {
+                "type": "string",
+                "description": "Install the wheel from this sandraschi/notepadpp-mcp GitHub release instead of PyPI."
+            },
+            "env": {
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+                "description": "Extra environment variables for the server. These override the project's .env file, which is read from working_directory."
+            },
+            "check_notepad_install": {
+                "type": "boolean",
+                "default": true,
+                "description": "Fail early on Windows when Notepad++ is not installed in a standard location and `notepad_path` is unset."
+            },
+            "instance_id": {
+                "type": "string",
+                "pattern": "^[A-Za-z0-9_-]+$",
+                "description": "Notepad++ instance the server attaches to. The default instance is used when omitted."
+            },
+            "enabled_tools": {
+                "type": "array",
+                "items": { "type": "string", "enum": TOOL_CATEGORIES },
+                "uniqueItems": true,
+                "description": "Tool categories the server exposes. All categories are enabled when omitted."
+            },
+            "launch_retries": {
+                "type": "integer",
+                "minimum": 1,
+                "default": 3,
+                "description": "Attempts made to find a launcher (uv, uvx, or Python) on PATH before giving up. Other launch errors are not retried."
+            },
+            "launch_retry_ms": {
+                "type": "integer",
+                "minimum": 0,
+                "default": 200,
+                "description": "Delay before the first retry in milliseconds; each further retry waits one more multiple of it."
+            },
+            "uv_cache_dir": {
+                "type": "string",
+                "description": "Cache directory for uv (UV_CACHE_DIR). Created if missing."
+            },
+            "uv_data_dir": {
+                "type": "string",
+                "description": "Data directory for uv (UV_DATA_DIR). Created if missing."
+            },
+            "http_proxy": {
+                "type": "string",
+                "format": "uri",
+                "description": "Proxy for HTTP package downloads. Defaults to the HTTP_PROXY environment variable."
+            },
+            "https_proxy": {
+                "type": "string",
+                "format": "uri",
+                "description": "Proxy for HTTPS package downloads. Defaults to the HTTPS_PROXY environment variable."
+            },
+            "no_proxy": {
+                "type": "string",
+                "description": "Comma-separated hosts that bypass the proxies. Defaults to the NO_PROXY environment variable."
+            }
+        }
+    });
+
+    Ok(zed::ContextServerConfiguration {
+        installation_instructions: INSTALLATION_INSTRUCTIONS.to_string(),
+        default_settings: to_string_pretty(&default_settings).map_err(|err| err.to_string())?,
+        settings_schema: to_string_pretty(&settings_schema).map_err(|err| err.to_string())?,
+    })
+}
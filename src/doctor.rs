@@ -0,0 +1,203 @@
+use zed_extension_api as zed;
+
+use crate::host;
+use crate::install;
+use crate::launcher::{self, LaunchRecord, Version, PACKAGE_NAME};
+use crate::notepad;
+use crate::platform::Platform;
+use crate::settings::{ServerSettings, Transport};
+
+/// Outcome of a single pre-flight check.
+struct Check {
+    name: &'static str,
+    /// Details on success, or the problem and how to fix it on failure.
+    outcome: Result<String, (String, String)>,
+}
+
+/// Runs the launch path's pre-flight checks and renders them as a report,
+/// along with the fingerprint of `last_launch` and, when known, the
+/// fingerprint of what a launch would run now.
+pub fn report(
+    settings: &ServerSettings,
+    platform: Platform,
+    last_launch: Option<&LaunchRecord>,
+    current: Option<zed::Result<u64>>,
+    uv_version: Option<Version>,
+    installed: &mut install::InstalledCache,
+) -> String {
+    let checks = [
+        check_platform(settings, platform),
+        check_uv(uv_version),
+        check_package(settings, uv_version, installed),
+        check_notepad(settings, platform),
+    ];
+
+    let mut report = String::from("notepadpp-mcp doctor\n\n");
+    for check in &checks {
+        match &check.outcome {
+            Ok(details) => report.push_str(&format!("[OK]   {}: {details}\n", check.name)),
+            Err((problem, hint)) => {
+                report.push_str(&format!("[FAIL] {}: {problem}\n", check.name));
+                report.push_str(&format!("       {hint}\n"));
+            }
+        }
+    }
+
+    match last_launch {
+        Some(launch) => report.push_str(&format!(
+            "\nConfig fingerprint: {:016x} (last launch of {})\n\
+             Zed settings edited since then are not reflected here until the server restarts.\n",
+            launch.fingerprint, launch.id
+        )),
+        None => report.push_str("\nConfig fingerprint: none, no server launched yet\n"),
+    }
+
+    let mut warnings = Vec::new();
+    if !settings.unknown_keys.is_empty() {
+        warnings.push(format!(
+            "ignored unknown settings: {}",
+            settings.unknown_keys.join(", ")
+        ));
+    }
+    if settings.project_root().is_none() {
+        warnings.push(
+            "`working_directory` is not set to the project's path, so the project's .env and .notepadpp-mcp.toml are not read".to_string(),
+        );
+    }
+    if let (Some(launch), Some(current)) = (last_launch, current) {
+        match current {
+            Ok(fingerprint) if fingerprint != launch.fingerprint => warnings.push(format!(
+                "the project file, .env, or environment changed since the last launch (now {fingerprint:016x}): restart the {} context server to apply it",
+                launch.id
+            )),
+            Ok(_) => {}
+            Err(err) => warnings.push(format!(
+                "could not resolve the current configuration: {err}"
+            )),
+        }
+    }
+    if !warnings.is_empty() {
+        report.push_str("\nWarnings:\n");
+        for warning in &warnings {
+            report.push_str(&format!("[WARN] {warning}\n"));
+        }
+    }
+    report
+}
+
+fn check_platform(settings: &ServerSettings, platform: Platform) -> Check {
+    let os = match platform.os {
+        zed::Os::Windows => "Windows",
+        zed::Os::Linux if platform.wsl => "Linux (WSL)",
+        zed::Os::Linux => "Linux",
+        zed::Os::Mac => "macOS",
+    };
+    let outcome = match notepad::check_platform(settings, platform) {
+        Ok(()) => Ok(match (&settings.ssh_target, &settings.wine_prefix) {
+            (Some(target), _) => format!("{os}, remote Windows over SSH ({target})"),
+            _ if settings.transport == Transport::Http => {
+                format!("{os}, server reached over http")
+            }
+            (None, Some(prefix)) => format!("{os}, Wine prefix {prefix}"),
+            (None, None) => os.to_string(),
+        }),
+        Err(err) => Err((
+            err.to_string(),
+            "Set one of these in the notepadpp-mcp context server settings.".to_string(),
+        )),
+    };
+    Check {
+        name: "Platform",
+        outcome,
+    }
+}
+
+/// Checks the `uv` on `PATH`, reusing the version detected by earlier
+/// launches when there is one.
+fn check_uv(mut uv_version: Option<Version>) -> Check {
+    let outcome = if host::is_available("uv") {
+        launcher::check_uv_version("uv", &mut uv_version)
+            .map(|version| version.to_string())
+            .map_err(|err| {
+                (
+                    err.to_string(),
+                    "Upgrade uv with `uv self update` or reinstall it.".to_string(),
+                )
+            })
+    } else {
+        Err((
+            "not found on PATH".to_string(),
+            "Install uv from https://docs.astral.sh/uv/ or set `python_path`.".to_string(),
+        ))
+    };
+    Check {
+        name: "uv",
+        outcome,
+    }
+}
+
+/// Checks the package the way a launch would: in the environment of the
+/// launcher it resolves to. Launches over SSH or http install nothing
+/// locally, so there is nothing to check for them.
+fn check_package(
+    settings: &ServerSettings,
+    mut uv_version: Option<Version>,
+    installed: &mut install::InstalledCache,
+) -> Check {
+    let remote = match (&settings.ssh_target, settings.http_url()) {
+        (Some(target), _) => Some(format!("N/A, `uv run` provides it on {target}")),
+        (None, Some(_)) => Some("N/A, the server is reached over http".to_string()),
+        (None, None) => None,
+    };
+    if let Some(details) = remote {
+        return Check {
+            name: "notepadpp-mcp package",
+            outcome: Ok(details),
+        };
+    }
+
+    let install_hint = if settings.auto_install() {
+        format!("It will be installed on the next launch, or run `pip install {PACKAGE_NAME}`.")
+    } else {
+        format!("Run `pip install {PACKAGE_NAME}` or enable `auto_install`.")
+    };
+    let outcome = match launcher::resolve_server(settings, &mut uv_version) {
+        Ok(command) => match install::is_package_installed(settings, &command, installed) {
+            Some(true) => Ok(format!("installed for `{}`", command.command)),
+            Some(false) => Err((
+                format!("not installed for `{}`", command.command),
+                install_hint,
+            )),
+            None => Ok(format!("fetched by `{}` when it starts", command.command)),
+        },
+        Err(err) => Err((format!("could not be checked: {err}"), install_hint)),
+    };
+    Check {
+        name: "notepadpp-mcp package",
+        outcome,
+    }
+}
+
+fn check_notepad(settings: &ServerSettings, platform: Platform) -> Check {
+    let outcome = match notepad::configured_path(settings, platform) {
+        Ok(Some(path)) => Ok(path),
+        Err(err) => Err((err, "Fix or remove `notepad_path`.".to_string())),
+        // The standard locations only exist on native Windows; elsewhere the
+        // server does its own search.
+        Ok(None) if platform.os != zed::Os::Windows => {
+            Ok("not configured, left to the server to find".to_string())
+        }
+        Ok(None) => match notepad::default_notepad_path() {
+            Some(path) => Ok(path.display().to_string()),
+            None => Err((
+                "not found in the standard install locations or on PATH".to_string(),
+                "Install Notepad++ from https://notepad-plus-plus.org or set `notepad_path`."
+                    .to_string(),
+            )),
+        },
+    };
+    Check {
+        name: "Notepad++",
+        outcome,
+    }
+}
@@ -0,0 +1,400 @@
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use zed_extension_api as zed;
+
+use crate::error::LaunchError;
+use crate::notepad;
+use crate::platform::Platform;
+use crate::settings::{ServerSettings, Transport};
+
+/// Variables a stdio server receives unless the `.env` file or the settings
+/// override them.
+const DEFAULT_ENV: &[(&str, &str)] = &[
+    ("NOTEPADPP_LOG_LEVEL", "INFO"),
+    ("NOTEPADPP_APPEARANCE", "auto"),
+];
+
+/// Variable carrying the extension instance's session id, so logs of servers
+/// started from different Zed windows can be told apart.
+pub const SESSION_ID_VAR: &str = "NOTEPADPP_SESSION_ID";
+
+/// Returns a short random id identifying this extension instance.
+pub fn new_session_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    if let Ok(elapsed) = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// Adds `NOTEPADPP_SESSION_ID` to `env` unless it is already set explicitly.
+pub fn add_session_id(env: &mut zed::EnvVars, session_id: &str) {
+    if !env.iter().any(|(key, _)| key == SESSION_ID_VAR) {
+        env.push((SESSION_ID_VAR.to_string(), session_id.to_string()));
+    }
+}
+
+/// Builds the launched server's environment from, in increasing precedence,
+/// [`DEFAULT_ENV`], the project's `.env` file, and the settings. Each key
+/// appears once, with the value from the highest-precedence layer.
+pub fn build_env(settings: &ServerSettings, platform: Platform) -> zed::Result<zed::EnvVars> {
+    let mut env = Vec::new();
+    // A server reached over HTTP is configured where it runs; the local bridge
+    // needs none of its defaults.
+    if settings.transport == Transport::Stdio {
+        merge_env(
+            &mut env,
+            DEFAULT_ENV
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        );
+    }
+    if let Some(root) = settings.project_root() {
+        merge_env(&mut env, dotenv_env(&root)?);
+    }
+    merge_env(&mut env, settings_env(settings, platform)?);
+    Ok(env)
+}
+
+/// Proxy variables from the settings, falling back to the ones Zed was started
+/// with, so `uv` and `pip` can reach the package index.
+pub fn proxy_env(settings: &ServerSettings) -> zed::EnvVars {
+    [
+        ("HTTP_PROXY", &settings.http_proxy),
+        ("HTTPS_PROXY", &settings.https_proxy),
+        ("NO_PROXY", &settings.no_proxy),
+    ]
+    .into_iter()
+    .filter_map(|(variable, setting)| {
+        let value = setting.clone().or_else(|| {
+            env::var(variable)
+                .or_else(|_| env::var(variable.to_ascii_lowercase()))
+                .ok()
+        })?;
+        Some((variable.to_string(), value))
+    })
+    .collect()
+}
+
+/// Variables directing `uv` at the configured cache and data directories.
+/// Shared by the launched server and the install commands so both write to
+/// the same place.
+pub fn uv_env(settings: &ServerSettings) -> zed::EnvVars {
+    uv_dirs(settings)
+        .into_iter()
+        .filter_map(|(_, variable, dir)| Some((variable.to_string(), dir.clone()?)))
+        .collect()
+}
+
+/// Creates the configured `uv` cache and data directories if missing.
+pub fn create_uv_dirs(settings: &ServerSettings) -> Result<(), LaunchError> {
+    for (setting, _, dir) in uv_dirs(settings) {
+        if let Some(dir) = dir {
+            fs::create_dir_all(dir).map_err(|err| {
+                LaunchError::BadPath(format!("Failed to create `{setting}` {dir:?}: {err}"))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn uv_dirs(settings: &ServerSettings) -> [(&'static str, &'static str, &Option<String>); 2] {
+    [
+        ("uv_cache_dir", "UV_CACHE_DIR", &settings.uv_cache_dir),
+        ("uv_data_dir", "UV_DATA_DIR", &settings.uv_data_dir),
+    ]
+}
+
+/// Variables derived from the extension settings, followed by the explicit
+/// `env` entries.
+fn settings_env(settings: &ServerSettings, platform: Platform) -> zed::Result<zed::EnvVars> {
+    let mut env = zed::EnvVars::new();
+
+    if settings.transport == Transport::Stdio {
+        if let Some(level) = &settings.log_level {
+            env.push(("NOTEPADPP_LOG_LEVEL".to_string(), level.clone()));
+        }
+
+        if let Some(appearance) = settings.appearance {
+            env.push((
+                "NOTEPADPP_APPEARANCE".to_string(),
+                appearance.as_str().to_string(),
+            ));
+        }
+
+        if let Some(target) = &settings.ssh_target {
+            if let Some(notepad_path) = notepad::server_path(settings, platform)? {
+                env.push(("NOTEPADPP_PATH".to_string(), notepad_path));
+            }
+            // The server runs on the remote Windows machine, where
+            // `allowed_roots` live too, so they are passed along unchecked and
+            // joined the Windows way.
+            let roots = settings.remote_allowed_roots(target)?;
+            if !roots.is_empty() {
+                env.push(("NOTEPADPP_ALLOWED_ROOTS".to_string(), roots.join(";")));
+            }
+        } else {
+            env.extend(local_notepad_env(settings, platform)?);
+            env.extend(allowed_roots_env(settings)?);
+        }
+
+        if let Some(instance) = &settings.instance_id {
+            env.push(("NOTEPADPP_INSTANCE".to_string(), instance.clone()));
+        }
+
+        if let Some(tools) = &settings.enabled_tools {
+            env.push(("NOTEPADPP_ENABLED_TOOLS".to_string(), tools.join(",")));
+        }
+    }
+
+    // The cache and data directories are local paths, meaningless remotely.
+    if settings.ssh_target.is_none() {
+        env.extend(uv_env(settings));
+    }
+    env.extend(proxy_env(settings));
+    env.extend(
+        settings
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone())),
+    );
+    // An explicit `env` entry wins over the variable a setting derives.
+    Ok(dedup_env(env))
+}
+
+/// Forwards the canonicalized `allowed_roots` to a locally spawned server,
+/// joined with the platform's path separator.
+fn allowed_roots_env(settings: &ServerSettings) -> zed::Result<zed::EnvVars> {
+    let roots = settings.allowed_roots()?;
+    if roots.is_empty() {
+        return Ok(zed::EnvVars::new());
+    }
+    let joined =
+        env::join_paths(&roots).map_err(|err| LaunchError::UnforwardableRoots(err.to_string()))?;
+    Ok(vec![(
+        "NOTEPADPP_ALLOWED_ROOTS".to_string(),
+        joined.to_string_lossy().into_owned(),
+    )])
+}
+
+/// Tells a locally spawned server where and how to run Notepad++.
+fn local_notepad_env(settings: &ServerSettings, platform: Platform) -> zed::Result<zed::EnvVars> {
+    let mut env = zed::EnvVars::new();
+    // Notepad++ only runs natively on Windows. Under WSL the Windows install is
+    // reachable through `/mnt`, so the server only needs to know it is in WSL.
+    // Elsewhere it has to live in a Wine prefix, and the server is told so it
+    // can spawn `wine notepad++.exe` instead of the executable itself.
+    match platform.os {
+        zed::Os::Windows => {}
+        zed::Os::Linux if platform.wsl => {
+            env.push(("NOTEPADPP_WSL".to_string(), "1".to_string()));
+        }
+        zed::Os::Mac | zed::Os::Linux => {
+            let Some(wine_prefix) = &settings.wine_prefix else {
+                return Err(LaunchError::NoWinePrefix.into());
+            };
+            env.push(("NOTEPADPP_PLATFORM".to_string(), "wine".to_string()));
+            env.push(("WINEPREFIX".to_string(), wine_prefix.clone()));
+        }
+    }
+
+    if let Some(notepad_path) = notepad::server_path(settings, platform)? {
+        env.push(("NOTEPADPP_PATH".to_string(), notepad_path));
+    }
+    Ok(env)
+}
+
+/// Reads `KEY=VALUE` pairs from the `.env` file in `root`, returning nothing
+/// when the file does not exist.
+fn dotenv_env(root: &Path) -> Result<zed::EnvVars, LaunchError> {
+    let path = root.join(".env");
+    match fs::read_to_string(&path) {
+        Ok(contents) => Ok(parse_dotenv(&contents)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(LaunchError::BadPath(format!(
+            "Failed to read {}: {err}",
+            path.display()
+        ))),
+    }
+}
+
+/// Parses `.env` contents, skipping blank lines, `#` comments, and lines
+/// without `=`. Values may be wrapped in single or double quotes, and a key
+/// assigned twice keeps its last value.
+fn parse_dotenv(contents: &str) -> zed::EnvVars {
+    let vars = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim();
+            if key.is_empty() {
+                return None;
+            }
+            Some((key.to_string(), unquote(value.trim()).to_string()))
+        })
+        .collect();
+    dedup_env(vars)
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| value.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(value)
+}
+
+/// Keeps only the last entry for each key in `vars`.
+fn dedup_env(vars: zed::EnvVars) -> zed::EnvVars {
+    let mut env = zed::EnvVars::new();
+    for var in vars {
+        merge_env(&mut env, vec![var]);
+    }
+    env
+}
+
+/// Appends `overrides` to `env`, replacing any entries with the same key.
+pub fn merge_env(env: &mut zed::EnvVars, overrides: zed::EnvVars) {
+    env.retain(|(key, _)| {
+        !overrides
+            .iter()
+            .any(|(override_key, _)| override_key == key)
+    });
+    env.extend(overrides);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    const WINDOWS: Platform = Platform {
+        os: zed::Os::Windows,
+        wsl: false,
+    };
+
+    fn settings_in(dir: &Path) -> ServerSettings {
+        ServerSettings {
+            working_directory: Some(dir.to_string_lossy().into_owned()),
+            ..ServerSettings::default()
+        }
+    }
+
+    /// Builds the environment minus the proxy variables, which fall back to the
+    /// ambient environment the tests do not control.
+    fn launch_env(settings: &ServerSettings) -> zed::EnvVars {
+        build_env(settings, WINDOWS)
+            .unwrap()
+            .into_iter()
+            .filter(|(key, _)| !key.ends_with("_PROXY"))
+            .collect()
+    }
+
+    #[test]
+    fn empty_settings_yield_defaults() {
+        let dir = scratch_dir("defaults");
+        assert_eq!(
+            launch_env(&settings_in(&dir)),
+            vec![
+                ("NOTEPADPP_LOG_LEVEL".to_string(), "INFO".to_string()),
+                ("NOTEPADPP_APPEARANCE".to_string(), "auto".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn settings_override_dotenv_override_defaults() {
+        let dir = scratch_dir("precedence");
+        fs::write(
+            dir.join(".env"),
+            "NOTEPADPP_LOG_LEVEL=WARNING\nSHARED=dotenv\nSHARED=dotenv-last\nDOTENV_ONLY=1\n",
+        )
+        .unwrap();
+
+        let mut settings = settings_in(&dir);
+        settings
+            .env
+            .insert("SHARED".to_string(), "settings".to_string());
+        assert_eq!(
+            launch_env(&settings),
+            vec![
+                ("NOTEPADPP_APPEARANCE".to_string(), "auto".to_string()),
+                ("NOTEPADPP_LOG_LEVEL".to_string(), "WARNING".to_string()),
+                ("DOTENV_ONLY".to_string(), "1".to_string()),
+                ("SHARED".to_string(), "settings".to_string()),
+            ]
+        );
+
+        settings.log_level = Some("DEBUG".to_string());
+        let env = launch_env(&settings);
+        assert!(env.contains(&("NOTEPADPP_LOG_LEVEL".to_string(), "DEBUG".to_string())));
+        assert_eq!(env.len(), 4);
+    }
+
+    #[test]
+    fn each_key_appears_once_within_a_layer() {
+        let dir = scratch_dir("duplicates");
+        fs::write(dir.join(".env"), "X=1\nX=2\n").unwrap();
+
+        let mut settings = settings_in(&dir);
+        settings.log_level = Some("DEBUG".to_string());
+        settings
+            .env
+            .insert("NOTEPADPP_LOG_LEVEL".to_string(), "ERROR".to_string());
+        let env = launch_env(&settings);
+
+        let values = |key: &str| {
+            env.iter()
+                .filter(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values("X"), ["2"]);
+        assert_eq!(values("NOTEPADPP_LOG_LEVEL"), ["ERROR"]);
+    }
+
+    #[test]
+    fn parses_dotenv_lines() {
+        let contents = "\
+# comment
+API_KEY=secret   
+
+export TOKEN = \"quoted value\"
+SINGLE='x=1'
+not a pair
+";
+        assert_eq!(
+            parse_dotenv(contents),
+            vec![
+                ("API_KEY".to_string(), "secret".to_string()),
+                ("TOKEN".to_string(), "quoted value".to_string()),
+                ("SINGLE".to_string(), "x=1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_prefers_overrides() {
+        let mut env = vec![
+            ("A".to_string(), "dotenv".to_string()),
+            ("B".to_string(), "dotenv".to_string()),
+        ];
+        merge_env(&mut env, vec![("A".to_string(), "settings".to_string())]);
+        assert_eq!(
+            env,
+            vec![
+                ("B".to_string(), "dotenv".to_string()),
+                ("A".to_string(), "settings".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::install::RELEASE_REPO;
+use crate::launcher::{MIN_UV_VERSION, PACKAGE_NAME, TEMPLATE_TOKENS};
+
+/// Ways launching a context server can fail. Converted into the `String`
+/// errors of `zed::Result` at the extension boundary.
+#[derive(Debug, PartialEq, Eq)]
+pub enum LaunchError {
+    /// The context server id is not one this extension provides.
+    UnknownServer(String),
+    /// The server was turned off through the `disabled` setting.
+    Disabled(String),
+    /// The settings value does not have the expected shape; holds the parser's
+    /// message.
+    MalformedSettings(String),
+    /// A setting has a value outside what it accepts.
+    InvalidSetting {
+        setting: &'static str,
+        value: String,
+        expected: String,
+    },
+    /// `transport` is `http` but `url` is not set.
+    NoUrl,
+    /// `working_directory` is `project_root` but no folder is open.
+    NoProjectRoot(String),
+    /// A setting needs the project's path, which is only known from an explicit
+    /// `working_directory`; holds what needed it.
+    NoWorkingDirectory(String),
+    /// `uv` is required to install the server but could not be found on `PATH`.
+    UvNotFound,
+    /// `uv --version` could not be run or its output not understood.
+    UvVersionUnknown(String),
+    /// The `uv` on `PATH` is older than [`MIN_UV_VERSION`].
+    UvTooOld { found: String },
+    /// None of `uv`, `uvx`, `python3`, or `python` could be found on `PATH`.
+    NoLauncher,
+    /// `package_version` is set but the launcher cannot pin a version.
+    PinUnsupported { version: String, launcher: String },
+    /// `with_packages` is set but the launcher cannot add packages.
+    PackagesUnsupported { packages: String, launcher: String },
+    /// The server package is missing and `offline` forbids downloading it.
+    OfflinePackageMissing,
+    /// `notepadpp-mcp-dev` was started without `dev_repo_path`.
+    NoDevRepo,
+    /// `notepadpp-mcp-dev` was configured with `ssh_target`.
+    DevOverSsh,
+    /// Notepad++ would run under Wine but `wine_prefix` is not set.
+    NoWinePrefix,
+    /// A configured path is missing or of the wrong kind, or could not be read
+    /// or created; holds the details.
+    BadPath(String),
+    /// The project's `.notepadpp-mcp.toml` could not be parsed.
+    InvalidProjectFile { path: String, details: String },
+    /// An `allowed_roots` entry lies outside the project and
+    /// `allow_external_roots` is off. `project` is the project root, or where
+    /// it lives when that is a remote machine.
+    ExternalRoot { entry: String, project: String },
+    /// The canonicalized `allowed_roots` cannot be joined into one variable;
+    /// holds the reason.
+    UnforwardableRoots(String),
+    /// An `args_template` entry has a `${` without a closing `}`.
+    UnterminatedToken { entry: String },
+    /// An `args_template` entry uses a token outside [`TEMPLATE_TOKENS`].
+    UnknownToken { entry: String, token: String },
+    /// An `args_template` entry uses a token whose `setting` is not set.
+    UnsetToken {
+        entry: String,
+        token: String,
+        setting: String,
+    },
+    /// A settings-derived argument could not be passed through safely.
+    BadArgument { arg: String, reason: &'static str },
+    /// Zed runs on macOS or Linux and nothing is set up to reach a Notepad++.
+    UnsupportedPlatform { os: &'static str },
+    /// Notepad++ is not installed in any of the searched locations.
+    NotepadNotFound { searched: Vec<PathBuf> },
+    /// The wheel of the GitHub release `tag` could not be fetched.
+    ReleaseUnavailable { tag: String, details: String },
+    /// A helper command could not be started.
+    CommandFailed { program: String, details: String },
+    /// `probe_on_launch` is set but no Python is on `PATH` to run the probe.
+    ProbeNeedsPython,
+    /// The probed server exited without answering the MCP handshake.
+    ProbeExited { program: String },
+    /// The probed server answered the handshake with something other than a
+    /// JSON-RPC response.
+    ProbeUnexpectedOutput { program: String, output: String },
+    /// The probed server did not answer the handshake within `secs` seconds.
+    ProbeTimedOut { program: String, secs: u64 },
+    /// The probe itself failed; holds its error output.
+    ProbeFailed { program: String, details: String },
+    /// The http server's health endpoint did not respond successfully.
+    HealthCheckFailed { url: String, details: String },
+    /// An install command exited unsuccessfully.
+    InstallFailed {
+        what: String,
+        status: String,
+        output: String,
+    },
+}
+
+impl LaunchError {
+    /// Whether the error may clear up on its own, such as a launcher that is
+    /// not on `PATH` yet while a toolchain manager finishes setting it up.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::NoLauncher | Self::UvNotFound)
+    }
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownServer(id) => write!(f, "Unknown server: {id}"),
+            Self::Disabled(id) => write!(
+                f,
+                "{id} is disabled via settings: set `disabled` to false to start it"
+            ),
+            Self::MalformedSettings(details) => {
+                write!(f, "Invalid notepadpp-mcp settings: {details}")
+            }
+            Self::InvalidSetting {
+                setting,
+                value,
+                expected,
+            } => write!(f, "Invalid `{setting}` {value:?}: {expected}"),
+            Self::NoUrl => write!(
+                f,
+                "`transport` is \"http\" but `url` is not set: add the server's MCP endpoint, e.g. \"http://127.0.0.1:10815/mcp\""
+            ),
+            Self::NoProjectRoot(id) => write!(
+                f,
+                "`working_directory` is \"project_root\" but no folder is open: open a folder in Zed to start {id}"
+            ),
+            Self::NoWorkingDirectory(what) => write!(
+                f,
+                "{what} needs the project's path, which Zed does not expose to extensions: set `working_directory` to it"
+            ),
+            Self::UvNotFound => write!(
+                f,
+                "`uv` is not on PATH: install it from https://docs.astral.sh/uv/"
+            ),
+            Self::UvVersionUnknown(details) => write!(f, "Could not determine the uv version: {details}"),
+            Self::UvTooOld { found } => write!(
+                f,
+                "uv {found} is too old: {PACKAGE_NAME} requires uv {MIN_UV_VERSION} or newer"
+            ),
+            Self::NoLauncher => write!(
+                f,
+                "Could not find a launcher for {PACKAGE_NAME}: none of `uv`, `uvx`, or `python3`/`python` is on PATH"
+            ),
+            Self::PinUnsupported { version, launcher } => write!(
+                f,
+                "`package_version` is {version:?}, but {launcher} runs whichever {PACKAGE_NAME} version is installed: install uv to pin the version, or remove `package_version`"
+            ),
+            Self::PackagesUnsupported { packages, launcher } => write!(
+                f,
+                "`with_packages` lists {packages}, but {launcher} only sees the packages already installed: install uv to add them, or remove `with_packages`"
+            ),
+            Self::OfflinePackageMissing => write!(
+                f,
+                "`offline` is enabled and {PACKAGE_NAME} is not installed: install it manually (e.g. `pip install {PACKAGE_NAME}`) or turn `offline` off"
+            ),
+            Self::NoDevRepo => write!(
+                f,
+                "The notepadpp-mcp-dev context server requires `dev_repo_path` to point at a local checkout"
+            ),
+            Self::DevOverSsh => write!(
+                f,
+                "The notepadpp-mcp-dev context server runs a local checkout and does not support `ssh_target`"
+            ),
+            Self::NoWinePrefix => write!(
+                f,
+                "Notepad++ requires Wine on macOS and Linux: set `wine_prefix` in the notepadpp-mcp context server settings"
+            ),
+            Self::BadPath(details) => f.write_str(details),
+            Self::InvalidProjectFile { path, details } => write!(f, "Invalid {path}: {details}"),
+            Self::ExternalRoot { entry, project } => write!(
+                f,
+                "`allowed_roots` entry {entry:?} is outside the project ({project}): set `allow_external_roots` to true to allow it"
+            ),
+            Self::UnforwardableRoots(reason) => {
+                write!(f, "Cannot forward `allowed_roots`: {reason}")
+            }
+            Self::UnterminatedToken { entry } => {
+                write!(f, "Unterminated `${{` in `args_template` entry {entry:?}")
+            }
+            Self::UnknownToken { entry, token } => write!(
+                f,
+                "Unknown token `${{{token}}}` in `args_template` entry {entry:?}. Known tokens: {}",
+                TEMPLATE_TOKENS.join(", ")
+            ),
+            Self::UnsetToken {
+                entry,
+                token,
+                setting,
+            } => write!(
+                f,
+                "`args_template` entry {entry:?} uses `${{{token}}}`, but `{setting}` is not set"
+            ),
+            Self::BadArgument { arg, reason } => write!(f, "Argument {arg:?} {reason}"),
+            Self::UnsupportedPlatform { os } => write!(
+                f,
+                "Notepad++ is Windows-only and cannot run natively on {os}. To use it from here, either:\n\
+                 - run it under Wine: install Notepad++ into a Wine prefix and set `wine_prefix`\n\
+                 - run the server on a Windows machine: set `ssh_target` to \"user@host\"\n\
+                 - connect to a server running elsewhere: set `transport` to \"http\" and `url`"
+            ),
+            Self::NotepadNotFound { searched } => {
+                writeln!(f, "Notepad++ was not found. Searched:")?;
+                for path in searched {
+                    writeln!(f, "  {}", path.display())?;
+                }
+                write!(
+                    f,
+                    "Install it from https://notepad-plus-plus.org, or set `notepad_path` (or `check_notepad_install: false`) in the notepadpp-mcp context server settings"
+                )
+            }
+            Self::ReleaseUnavailable { tag, details } => write!(
+                f,
+                "Cannot install the {tag} release of {RELEASE_REPO}: {details}"
+            ),
+            Self::CommandFailed { program, details } => {
+                write!(f, "Failed to run `{program}`: {details}")
+            }
+            Self::ProbeNeedsPython => write!(
+                f,
+                "Probe: `probe_on_launch` needs `python3` or `python` on PATH"
+            ),
+            Self::ProbeExited { program } => write!(
+                f,
+                "Probe: `{program}` exited before completing the MCP handshake"
+            ),
+            Self::ProbeUnexpectedOutput { program, output } => write!(
+                f,
+                "Probe: `{program}` answered the MCP handshake with unexpected output {output:?}"
+            ),
+            Self::ProbeTimedOut { program, secs } => write!(
+                f,
+                "Probe: `{program}` did not complete the MCP handshake within {secs}s"
+            ),
+            Self::ProbeFailed { program, details } => {
+                write!(f, "Probe failed to run `{program}`: {details}")
+            }
+            Self::HealthCheckFailed { url, details } => {
+                write!(f, "Probe: health check at {url} failed: {details}")
+            }
+            Self::InstallFailed {
+                what,
+                status,
+                output,
+            } => write!(f, "Installing {what} failed ({status}):\n{output}"),
+        }
+    }
+}
+
+impl From<LaunchError> for String {
+    fn from(err: LaunchError) -> Self {
+        err.to_string()
+    }
+}
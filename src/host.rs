@@ -0,0 +1,47 @@
+use zed::process::Output;
+use zed_extension_api as zed;
+
+/// Runs `command` to completion on the machine Zed runs on.
+///
+/// Extensions are sandboxed, so the program is started by Zed: it is looked up
+/// on Zed's `PATH` rather than the extension's, and must be allowed by one of
+/// the `process:exec` capabilities declared in `extension.toml`.
+#[cfg(not(test))]
+pub fn run(command: &zed::Command) -> zed::Result<Output> {
+    command.clone().output()
+}
+
+/// Unit tests run natively, where Zed's host functions are unavailable, so
+/// they spawn programs directly instead.
+#[cfg(test)]
+pub fn run(command: &zed::Command) -> zed::Result<Output> {
+    let output = std::process::Command::new(&command.command)
+        .args(&command.args)
+        .envs(command.env.iter().cloned())
+        .output()
+        .map_err(|err| err.to_string())?;
+    Ok(Output {
+        status: output.status.code(),
+        stdout: output.stdout,
+        stderr: output.stderr,
+    })
+}
+
+/// Whether `program` can be started through [`run`]. Its exit status does not
+/// matter, only that it was found and allowed to run.
+pub fn is_available(program: &str) -> bool {
+    run(&zed::Command::new(program).arg("--version")).is_ok()
+}
+
+/// Whether `output` comes from a process that exited successfully.
+pub fn succeeded(output: &Output) -> bool {
+    output.status == Some(0)
+}
+
+/// Describes how the process behind `output` exited, for error messages.
+pub fn exit_status(output: &Output) -> String {
+    match output.status {
+        Some(code) => format!("exit code {code}"),
+        None => "terminated by a signal".to_string(),
+    }
+}
@@ -0,0 +1,412 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use zed::process::Output;
+use zed_extension_api as zed;
+
+use crate::environment::{proxy_env, uv_env};
+use crate::error::LaunchError;
+use crate::host;
+use crate::launcher::{self, sanitize_arg, PackageSource, PACKAGE_NAME, SERVER_MODULE};
+use crate::settings::ServerSettings;
+
+/// GitHub repository publishing server wheels as release assets.
+pub const RELEASE_REPO: &str = "sandraschi/notepadpp-mcp";
+
+/// Checks that the server package can be imported.
+const IMPORT_CHECK: &str = "import notepadpp_mcp";
+
+/// The outcome of the last "is the package installed?" probe, reused until it
+/// is older than `install_check_ttl_secs` or the settings change.
+#[derive(Default)]
+pub struct InstalledCache {
+    last: Option<(Instant, u64, bool)>,
+}
+
+impl InstalledCache {
+    /// Returns the cached result for `settings` if it is younger than `ttl`,
+    /// otherwise runs `probe` and caches its result.
+    fn get_or_probe(
+        &mut self,
+        settings: &ServerSettings,
+        ttl: Duration,
+        probe: impl FnOnce() -> bool,
+    ) -> bool {
+        let fingerprint = settings.fingerprint();
+        if let Some((checked, cached, installed)) = self.last {
+            if cached == fingerprint && checked.elapsed() < ttl {
+                return installed;
+            }
+        }
+
+        let installed = probe();
+        self.last = Some((Instant::now(), fingerprint, installed));
+        installed
+    }
+
+    fn invalidate(&mut self) {
+        self.last = None;
+    }
+}
+
+/// Makes sure `command`, the resolved launcher, can run the server package
+/// when `auto_install` is enabled, using the PyPI package or the
+/// `release_tag` wheel:
+///
+/// - `uvx` and a pinned `uv run` fetch the package themselves; `uvx` is only
+///   pointed at the release wheel.
+/// - An unpinned `uv run` whose environment lacks the package gets it through
+///   `--with`, so nothing is installed into a system Python.
+/// - A Python interpreter has the package installed into it. In `offline`
+///   mode only a previously downloaded release wheel may be installed.
+pub fn ensure_installed(
+    settings: &ServerSettings,
+    command: &mut zed::Command,
+    cache: &mut InstalledCache,
+) -> zed::Result<()> {
+    if !settings.auto_install() {
+        return Ok(());
+    }
+
+    let source = launcher::package_source(settings, command);
+    if source == PackageSource::Launcher {
+        if let (Some(tag), "uvx") = (&settings.release_tag, command.command.as_str()) {
+            launcher::uvx_from(command, &release_wheel(tag, settings.offline())?);
+        }
+        return Ok(());
+    }
+    if is_package_installed(settings, command, cache) != Some(false) {
+        return Ok(());
+    }
+
+    let target = match &settings.release_tag {
+        Some(tag) => release_wheel(tag, settings.offline())?,
+        None if settings.offline() && source != PackageSource::UvRun => {
+            return Err(LaunchError::OfflinePackageMissing.into());
+        }
+        None => PACKAGE_NAME.to_string(),
+    };
+    match source {
+        PackageSource::UvRun => {
+            *command =
+                launcher::uv_run_with(command, ["--with", &target, "python", "-m", SERVER_MODULE]);
+        }
+        PackageSource::Interpreter(python) => {
+            let mut env = uv_env(settings);
+            env.extend(proxy_env(settings));
+            env.extend(timeout_env(settings.install_timeout()));
+            let install = install_command(
+                &target,
+                &python,
+                settings.python_path.is_some(),
+                host::is_available("uv"),
+                settings.offline(),
+            );
+            run_install(install, &target, &env)?;
+            cache.invalidate();
+        }
+        PackageSource::Remote | PackageSource::Launcher => {}
+    }
+    Ok(())
+}
+
+/// Whether the environment `command` runs the server in can import the
+/// server package, or `None` when the launcher fetches the package itself or
+/// the server runs elsewhere. An unpinned `uv run` is checked through
+/// `uv run python`, an interpreter directly. The result is reused from
+/// `cache` while it is fresh, so launches and `/notepad-doctor` share one
+/// probe.
+pub fn is_package_installed(
+    settings: &ServerSettings,
+    command: &zed::Command,
+    cache: &mut InstalledCache,
+) -> Option<bool> {
+    let probe = match launcher::package_source(settings, command) {
+        PackageSource::Remote | PackageSource::Launcher => return None,
+        PackageSource::UvRun => {
+            launcher::uv_run_with(command, ["python", "-c", IMPORT_CHECK]).envs(uv_env(settings))
+        }
+        PackageSource::Interpreter(python) => zed::Command::new(python).args(["-c", IMPORT_CHECK]),
+    };
+    Some(
+        cache.get_or_probe(settings, settings.install_check_ttl(), || {
+            host::run(&probe).is_ok_and(|output| host::succeeded(&output))
+        }),
+    )
+}
+
+/// The command installing `target`, a requirement or wheel, into `python`,
+/// the interpreter that runs the server. A `configured` interpreter, such as
+/// a virtualenv, receives it directly, otherwise the user site-packages do.
+/// `uv` is preferred, falling back to `python`'s own pip.
+fn install_command(
+    target: &str,
+    python: &str,
+    configured: bool,
+    has_uv: bool,
+    offline: bool,
+) -> zed::Command {
+    let command = if has_uv {
+        zed::Command::new("uv").args(["pip", "install", "--python", python])
+    } else {
+        zed::Command::new(python).args(["-m", "pip", "install"])
+    };
+    // A virtualenv has no user site-packages to install into, and `uv`
+    // installs into the interpreter's environment anyway.
+    let command = if configured || has_uv {
+        command
+    } else {
+        command.arg("--user")
+    };
+    let command = match (offline, has_uv) {
+        (false, _) => command,
+        (true, true) => command.arg("--offline"),
+        (true, false) => command.arg("--no-index"),
+    };
+    command.arg(target)
+}
+
+/// Bounds how long `uv` and `pip` wait on a stalled download. This is only a
+/// network timeout: `zed::process` cannot spawn a command in the background
+/// or kill it, so an install that hangs for any other reason still blocks.
+fn timeout_env(timeout: Duration) -> zed::EnvVars {
+    let secs = timeout.as_secs().to_string();
+    vec![
+        ("UV_HTTP_TIMEOUT".to_string(), secs.clone()),
+        ("PIP_TIMEOUT".to_string(), secs),
+    ]
+}
+
+/// The full path of the wheel attached to the GitHub release `tag`. Wheels
+/// are kept under `wheels/<tag>/` so later launches reuse the download.
+fn release_wheel(tag: &str, offline: bool) -> zed::Result<String> {
+    let wheel = match cached_wheel(tag) {
+        Some(wheel) => wheel,
+        None if offline => return Err(LaunchError::OfflinePackageMissing.into()),
+        None => download_wheel(tag)?,
+    };
+    // Launchers do not run in the extension's directory, so they need the
+    // full path.
+    let wheel = env::current_dir()
+        .map(|dir| dir.join(&wheel))
+        .unwrap_or(wheel);
+    Ok(sanitize_arg(&wheel.to_string_lossy())?)
+}
+
+fn release_dir(tag: &str) -> PathBuf {
+    Path::new("wheels").join(tag)
+}
+
+fn cached_wheel(tag: &str) -> Option<PathBuf> {
+    fs::read_dir(release_dir(tag))
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| path.extension().is_some_and(|extension| extension == "whl"))
+}
+
+fn download_wheel(tag: &str) -> Result<PathBuf, LaunchError> {
+    let unavailable = |details: String| LaunchError::ReleaseUnavailable {
+        tag: tag.to_string(),
+        details,
+    };
+    let release = zed::github_release_by_tag_name(RELEASE_REPO, tag)
+        .map_err(|err| unavailable(format!("fetching it failed: {err}")))?;
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name.ends_with(".whl"))
+        .ok_or_else(|| unavailable("it has no .whl asset".to_string()))?;
+
+    let dir = release_dir(tag);
+    fs::create_dir_all(&dir).map_err(|err| {
+        LaunchError::BadPath(format!("Failed to create {}: {err}", dir.display()))
+    })?;
+    let wheel = dir.join(&asset.name);
+    zed::download_file(
+        &asset.download_url,
+        &wheel.to_string_lossy(),
+        zed::DownloadedFileType::Uncompressed,
+    )
+    .map_err(|err| unavailable(format!("downloading {} failed: {err}", asset.download_url)))?;
+    Ok(wheel)
+}
+
+/// Runs an install command, turning a failure into an error carrying its
+/// output.
+fn run_install(command: zed::Command, what: &str, env: &zed::EnvVars) -> zed::Result<()> {
+    let command = command.envs(env.iter().cloned());
+    let output = host::run(&command).map_err(|details| LaunchError::CommandFailed {
+        program: command.command.clone(),
+        details,
+    })?;
+    if !host::succeeded(&output) {
+        return Err(LaunchError::InstallFailed {
+            what: what.to_string(),
+            status: host::exit_status(&output),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Returns the installed server package version reported by
+/// `uv pip show`, or `None` when the package is not installed. The package
+/// is looked up in the `python_path` interpreter's environment when set.
+pub fn installed_version(settings: &ServerSettings) -> zed::Result<Option<String>> {
+    if !host::is_available("uv") {
+        return Err(LaunchError::UvNotFound.into());
+    }
+    let python = match &settings.python_path {
+        Some(python_path) => Some(
+            launcher::check_interpreter(python_path, settings)?
+                .to_string_lossy()
+                .into_owned(),
+        ),
+        None => None,
+    };
+    let mut args = vec!["pip", "show"];
+    if let Some(python) = &python {
+        args.extend(["--python", python]);
+    }
+    args.push(PACKAGE_NAME);
+    let output = run("uv", &args).map_err(|details| LaunchError::CommandFailed {
+        program: "uv".to_string(),
+        details,
+    })?;
+    if !host::succeeded(&output) {
+        return Ok(None);
+    }
+    Ok(parse_show_version(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_show_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Version:"))
+        .map(|version| version.trim().to_string())
+}
+
+fn run(program: &str, args: &[&str]) -> zed::Result<Output> {
+    host::run(&zed::Command::new(program).args(args.iter().copied()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probes_once_within_the_ttl() {
+        let mut cache = InstalledCache::default();
+        let settings = ServerSettings::default();
+        let mut probes = 0;
+
+        for _ in 0..2 {
+            let installed = cache.get_or_probe(&settings, Duration::from_secs(300), || {
+                probes += 1;
+                true
+            });
+            assert!(installed);
+        }
+
+        assert_eq!(probes, 1);
+    }
+
+    /// How the server is started when only a bare `python3` is on `PATH`.
+    fn interpreter_launcher() -> zed::Command {
+        zed::Command::new("python3").args(["-m", SERVER_MODULE])
+    }
+
+    #[test]
+    fn offline_mode_refuses_to_install_a_missing_package() {
+        let settings = ServerSettings {
+            offline: Some(true),
+            ..ServerSettings::default()
+        };
+        let mut cache = InstalledCache::default();
+        cache.get_or_probe(&settings, settings.install_check_ttl(), || false);
+
+        assert_eq!(
+            ensure_installed(&settings, &mut interpreter_launcher(), &mut cache),
+            Err(LaunchError::OfflinePackageMissing.to_string())
+        );
+    }
+
+    #[test]
+    fn skips_the_release_wheel_when_the_package_is_installed() {
+        let settings = ServerSettings {
+            release_tag: Some("v0.1.0".to_string()),
+            ..ServerSettings::default()
+        };
+        let mut cache = InstalledCache::default();
+        cache.get_or_probe(&settings, settings.install_check_ttl(), || true);
+
+        let mut command = interpreter_launcher();
+        assert_eq!(
+            ensure_installed(&settings, &mut command, &mut cache),
+            Ok(())
+        );
+        assert_eq!(command.args, ["-m", SERVER_MODULE]);
+    }
+
+    #[test]
+    fn uv_run_brings_its_own_package_instead_of_installing() {
+        let settings = ServerSettings::default();
+        let mut cache = InstalledCache::default();
+        cache.get_or_probe(&settings, settings.install_check_ttl(), || false);
+
+        let mut command =
+            zed::Command::new("uv").args(["run".to_string(), format!("{SERVER_MODULE}:run")]);
+        assert_eq!(
+            ensure_installed(&settings, &mut command, &mut cache),
+            Ok(())
+        );
+        assert_eq!(command.command, "uv");
+        assert_eq!(
+            command.args,
+            ["run", "--with", PACKAGE_NAME, "python", "-m", SERVER_MODULE]
+        );
+
+        let mut uvx = zed::Command::new("uvx").arg(PACKAGE_NAME);
+        assert_eq!(is_package_installed(&settings, &uvx, &mut cache), None);
+        assert_eq!(ensure_installed(&settings, &mut uvx, &mut cache), Ok(()));
+        assert_eq!(uvx.args, [PACKAGE_NAME]);
+    }
+
+    #[test]
+    fn installs_into_the_launching_interpreter() {
+        let venv = "/work/.venv/bin/python";
+        let uv = install_command(PACKAGE_NAME, venv, true, true, false);
+        assert_eq!(uv.command, "uv");
+        assert_eq!(uv.args, ["pip", "install", "--python", venv, PACKAGE_NAME]);
+
+        let pip = install_command(PACKAGE_NAME, venv, true, false, false);
+        assert_eq!(pip.command, venv);
+        assert_eq!(pip.args, ["-m", "pip", "install", PACKAGE_NAME]);
+
+        let system = install_command(PACKAGE_NAME, "python3", false, false, true);
+        assert_eq!(system.command, "python3");
+        assert_eq!(
+            system.args,
+            ["-m", "pip", "install", "--user", "--no-index", PACKAGE_NAME]
+        );
+    }
+
+    #[test]
+    fn install_timeout_bounds_uv_and_pip_downloads() {
+        assert_eq!(
+            timeout_env(Duration::from_secs(45)),
+            vec![
+                ("UV_HTTP_TIMEOUT".to_string(), "45".to_string()),
+                ("PIP_TIMEOUT".to_string(), "45".to_string()),
+            ]
+        );
+    }
+}
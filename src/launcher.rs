@@ -0,0 +1,1119 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use zed::serde_json::Value;
+use zed_extension_api as zed;
+
+use crate::environment::SESSION_ID_VAR;
+use crate::error::LaunchError;
+use crate::host;
+use crate::notepad;
+use crate::platform::Platform;
+use crate::settings::{
+    canonicalize_setting_path, PythonMode, ServerSettings, Transport, PROJECT_ROOT,
+};
+
+/// Python module that serves the MCP protocol over stdio.
+pub const SERVER_MODULE: &str = "notepadpp_mcp.tools.server";
+
+/// Name of the server package on PyPI.
+pub const PACKAGE_NAME: &str = "notepadpp-mcp";
+
+/// Oldest `uv` release supporting the `run` flags used here.
+pub const MIN_UV_VERSION: Version = Version {
+    major: 0,
+    minor: 4,
+    patch: 0,
+};
+
+/// A `major.minor.patch` release number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Resolved launcher commands keyed by context server id, reused while the
+/// settings that produced them are unchanged.
+#[derive(Default)]
+pub struct LauncherCache {
+    entries: HashMap<String, (u64, zed::Command)>,
+}
+
+impl LauncherCache {
+    /// Returns the cached command for `id` if it was resolved from identical
+    /// `settings`, otherwise runs `resolve` and caches its result.
+    pub fn get_or_resolve(
+        &mut self,
+        id: &str,
+        settings: &ServerSettings,
+        resolve: impl FnOnce(&ServerSettings) -> zed::Result<zed::Command>,
+    ) -> zed::Result<zed::Command> {
+        let fingerprint = settings.fingerprint();
+        if let Some((cached, command)) = self.entries.get(id) {
+            if *cached == fingerprint {
+                return Ok(command.clone());
+            }
+        }
+
+        let command = resolve(settings)?;
+        self.entries
+            .insert(id.to_string(), (fingerprint, command.clone()));
+        Ok(command)
+    }
+}
+
+/// What the most recent launch of a context server ran, kept so
+/// `/notepad-doctor` can tell when the configuration has drifted since.
+#[derive(Debug, Clone)]
+pub struct LaunchRecord {
+    /// Context server id that was launched.
+    pub id: String,
+    /// [`config_fingerprint`] of the launched command.
+    pub fingerprint: u64,
+    /// Raw settings the command was built from. Drift checks re-resolve these
+    /// rather than the current Zed settings, which slash commands cannot read.
+    pub settings: Option<Value>,
+    /// The user's own `command` block, when it replaced the default launcher.
+    pub user_command: Option<zed::Command>,
+}
+
+/// Hash of what a launch runs: the program, its arguments, and its
+/// environment. Variables are hashed in key order so the same configuration
+/// always yields the same fingerprint, and the session id, which differs
+/// between Zed windows, is left out, including where it is forwarded over SSH.
+pub fn config_fingerprint(command: &zed::Command) -> u64 {
+    let mut env: BTreeMap<&str, &str> = command
+        .env
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    let args: Vec<String> = match env.remove(SESSION_ID_VAR) {
+        Some(session_id) => {
+            let forwarded = format!("\"{SESSION_ID_VAR}={session_id}\"");
+            command
+                .args
+                .iter()
+                .map(|arg| arg.replace(&forwarded, ""))
+                .collect()
+        }
+        None => command.args.clone(),
+    };
+    let mut hasher = DefaultHasher::new();
+    command.command.hash(&mut hasher);
+    args.hash(&mut hasher);
+    env.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Calls `resolve` up to `attempts` times, sleeping `delay`, then twice
+/// `delay`, and so on between [transient](LaunchError::is_transient)
+/// failures. Any other error, or the last one, is returned as is.
+pub fn with_retries(
+    attempts: u32,
+    delay: Duration,
+    mut resolve: impl FnMut() -> Result<zed::Command, LaunchError>,
+) -> Result<zed::Command, LaunchError> {
+    let mut attempt = 1;
+    loop {
+        match resolve() {
+            Ok(command) => return Ok(command),
+            Err(err) if attempt >= attempts || !err.is_transient() => return Err(err),
+            Err(_) => {
+                thread::sleep(delay * attempt);
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Resolves the command that reaches the released server over its configured
+/// transport, retrying launcher lookup for stdio as the settings ask.
+pub fn resolve_server(
+    settings: &ServerSettings,
+    uv_version: &mut Option<Version>,
+) -> zed::Result<zed::Command> {
+    if let Some(url) = settings.http_url() {
+        return resolve_http_bridge(settings, url);
+    }
+    let resolved = with_retries(
+        settings.launch_retries(),
+        settings.launch_retry_delay(),
+        || resolve_launcher(settings, uv_version),
+    );
+    match resolved {
+        // Zed may not let the extension run anything to look launchers up, so
+        // fall back to whatever `uv` is on Zed's own PATH.
+        Err(LaunchError::NoLauncher) => Ok(uv_launcher(settings)?),
+        resolved => Ok(resolved?),
+    }
+}
+
+/// Finds the first usable launcher on Zed's `PATH`, preferring `uv`, then
+/// `uvx`, then a bare Python interpreter.
+///
+/// `uv_version` caches the detected `uv` version across calls.
+pub fn resolve_launcher(
+    settings: &ServerSettings,
+    uv_version: &mut Option<Version>,
+) -> Result<zed::Command, LaunchError> {
+    resolve_launcher_in(settings, uv_version, host::is_available)
+}
+
+fn resolve_launcher_in(
+    settings: &ServerSettings,
+    uv_version: &mut Option<Version>,
+    is_available: impl Fn(&str) -> bool,
+) -> Result<zed::Command, LaunchError> {
+    if let Some(target) = &settings.ssh_target {
+        return resolve_ssh_launcher(settings, target);
+    }
+
+    if let Some(python_path) = &settings.python_path {
+        let python = check_interpreter(python_path, settings)?;
+        require_unpinned(settings, "the `python_path` interpreter")?;
+        require_no_packages(settings, "the `python_path` interpreter")?;
+        return Ok(command(
+            python.to_string_lossy(),
+            python_args(working_directory(settings)?.as_deref())?,
+        ));
+    }
+
+    if is_available("uv") {
+        check_uv_version("uv", uv_version)?;
+        return uv_launcher(settings);
+    }
+
+    if is_available("uvx") {
+        let mut args = uv_flags(settings);
+        if let Some(dir) = working_directory(settings)? {
+            args.extend(["--directory".to_string(), sanitize_arg(&dir)?]);
+        }
+        for package in &settings.with_packages {
+            args.extend(["--with".to_string(), sanitize_arg(package)?]);
+        }
+        if let Some(requirement) = requirement(settings)? {
+            args.extend(["--from".to_string(), requirement]);
+        }
+        args.push(PACKAGE_NAME.to_string());
+        return Ok(command("uvx", args));
+    }
+
+    if let Some(python) = ["python3", "python"]
+        .into_iter()
+        .find(|python| is_available(python))
+    {
+        require_unpinned(settings, &format!("`{python}`"))?;
+        require_no_packages(settings, &format!("`{python}`"))?;
+        return Ok(command(
+            python,
+            python_args(working_directory(settings)?.as_deref())?,
+        ));
+    }
+
+    Err(LaunchError::NoLauncher)
+}
+
+/// Fails when `package_version` is set, since `launcher` runs whichever
+/// server version is installed and pinning needs uv.
+fn require_unpinned(settings: &ServerSettings, launcher: &str) -> Result<(), LaunchError> {
+    match &settings.package_version {
+        Some(version) => Err(LaunchError::PinUnsupported {
+            version: version.clone(),
+            launcher: launcher.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Fails when `with_packages` is set, since `launcher` runs in an existing
+/// environment and adding packages to it on the fly needs uv.
+fn require_no_packages(settings: &ServerSettings, launcher: &str) -> Result<(), LaunchError> {
+    match settings.with_packages.as_slice() {
+        [] => Ok(()),
+        packages => Err(LaunchError::PackagesUnsupported {
+            packages: packages.join(", "),
+            launcher: launcher.to_string(),
+        }),
+    }
+}
+
+/// Runs the server with `uv run`, pulling in `package_version` and
+/// `with_packages` on the fly.
+fn uv_launcher(settings: &ServerSettings) -> Result<zed::Command, LaunchError> {
+    let mut args = vec!["run".to_string()];
+    args.extend(uv_flags(settings));
+    // The extension API exposes a project's worktree ids but not their paths, so
+    // without an explicit `working_directory` the server keeps the directory Zed
+    // launches it in rather than the extension's sandbox.
+    if let Some(dir) = working_directory(settings)? {
+        args.extend(["--directory".to_string(), sanitize_arg(&dir)?]);
+    }
+    for package in &settings.with_packages {
+        args.extend(["--with".to_string(), sanitize_arg(package)?]);
+    }
+    match requirement(settings)? {
+        Some(requirement) => args.extend([
+            "--with".to_string(),
+            requirement,
+            "python".to_string(),
+            "-m".to_string(),
+            SERVER_MODULE.to_string(),
+        ]),
+        None => args.push(format!("{SERVER_MODULE}:run")),
+    }
+    Ok(command("uv", args))
+}
+
+/// Where a resolved launcher gets the server package from, which decides how
+/// an automatic install provides it.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackageSource {
+    /// The server runs elsewhere: over SSH, or behind an http `url`.
+    Remote,
+    /// `uvx` or a pinned `uv run --with`, which fetch the package themselves.
+    Launcher,
+    /// An unpinned `uv run`, whose environment may or may not have the package.
+    UvRun,
+    /// A Python interpreter importing the installed package.
+    Interpreter(String),
+}
+
+/// Classifies `command`, as resolved by [`resolve_server`] for `settings`.
+pub fn package_source(settings: &ServerSettings, command: &zed::Command) -> PackageSource {
+    if settings.ssh_target.is_some() || settings.http_url().is_some() {
+        return PackageSource::Remote;
+    }
+    match command.command.as_str() {
+        "uvx" => PackageSource::Launcher,
+        "uv" if command.args.last() == Some(&format!("{SERVER_MODULE}:run")) => {
+            PackageSource::UvRun
+        }
+        "uv" => PackageSource::Launcher,
+        python => PackageSource::Interpreter(python.to_string()),
+    }
+}
+
+/// Replaces what an unpinned `uv run` `command` runs with `args`, keeping its
+/// flags, so the result runs in the same environment.
+pub fn uv_run_with<'a>(
+    command: &zed::Command,
+    args: impl IntoIterator<Item = &'a str>,
+) -> zed::Command {
+    let mut command = command.clone();
+    command.args.pop();
+    command.args.extend(args.into_iter().map(str::to_string));
+    command
+}
+
+/// Makes `uvx` `command` fetch the server from `source`, such as a release
+/// wheel, unless it is already pinned with `--from`.
+pub fn uvx_from(command: &mut zed::Command, source: &str) {
+    if !command.args.iter().any(|arg| arg == "--from") {
+        let package = command.args.len() - 1;
+        command
+            .args
+            .splice(package..package, ["--from".to_string(), source.to_string()]);
+    }
+}
+
+/// The pinned server requirement, when `package_version` is set.
+fn requirement(settings: &ServerSettings) -> Result<Option<String>, LaunchError> {
+    settings
+        .package_version
+        .as_ref()
+        .map(|version| sanitize_arg(&format!("{PACKAGE_NAME}=={version}")))
+        .transpose()
+}
+
+/// Runs the server on `target` with `uv run` over `ssh`, which carries Zed's
+/// stdio transport. Local interpreter and install settings do not apply; the
+/// package is pulled in with `--with` on the remote side.
+fn resolve_ssh_launcher(
+    settings: &ServerSettings,
+    target: &str,
+) -> Result<zed::Command, LaunchError> {
+    let requirement = match &settings.package_version {
+        Some(version) => format!("{PACKAGE_NAME}=={version}"),
+        None => PACKAGE_NAME.to_string(),
+    };
+
+    let mut args = vec![
+        "-T".to_string(),
+        target.to_string(),
+        "uv".to_string(),
+        "run".to_string(),
+    ];
+    args.extend(uv_flags(settings));
+    if let Some(dir) = settings
+        .working_directory
+        .as_deref()
+        .filter(|dir| *dir != PROJECT_ROOT)
+    {
+        args.extend(["--directory".to_string(), remote_arg(dir)?]);
+    }
+    for package in settings.with_packages.iter().chain([&requirement]) {
+        args.extend(["--with".to_string(), remote_arg(package)?]);
+    }
+    args.extend([
+        "python".to_string(),
+        "-m".to_string(),
+        SERVER_MODULE.to_string(),
+    ]);
+    Ok(command("ssh", args))
+}
+
+/// Prepares an argument for the remote Windows shell, which receives the
+/// whole command as one string: anything beyond plain path and version
+/// characters is double-quoted.
+fn remote_arg(arg: &str) -> Result<String, LaunchError> {
+    let arg = sanitize_arg_for(arg, true)?;
+    if arg.contains('"') {
+        return Err(LaunchError::BadArgument {
+            arg,
+            reason: "cannot contain double quotes when run over SSH",
+        });
+    }
+    let is_plain = arg
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"-_.=/\\:,".contains(&b));
+    Ok(if is_plain { arg } else { format!("\"{arg}\"") })
+}
+
+/// Prefixes of the variables handed to a server run over SSH, which the
+/// remote sshd must list in `AcceptEnv`.
+const SSH_FORWARDED_PREFIXES: &[&str] = &["NOTEPADPP_", "UV_"];
+
+/// Hands the server's own `NOTEPADPP_*` and `UV_*` variables in
+/// `command.env` to the remote server through `ssh -o SetEnv`, since ssh does
+/// not forward the local environment. Everything else, such as `.env` secrets
+/// and proxy credentials, stays local: `SetEnv` puts values on the command
+/// line, where other users can see them.
+pub fn forward_env_over_ssh(
+    command: &mut zed::Command,
+    settings: &ServerSettings,
+) -> zed::Result<()> {
+    if settings.ssh_target.is_none() {
+        return Ok(());
+    }
+    let vars = command
+        .env
+        .iter()
+        .filter(|(key, _)| {
+            SSH_FORWARDED_PREFIXES
+                .iter()
+                .any(|prefix| key.starts_with(prefix))
+        })
+        .map(|(key, value)| {
+            let var = format!("{key}={value}");
+            if var.contains('"') {
+                return Err(LaunchError::BadArgument {
+                    arg: var,
+                    reason: "cannot contain double quotes when forwarded over SSH",
+                });
+            }
+            Ok(format!("\"{var}\""))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if vars.is_empty() {
+        return Ok(());
+    }
+    command.args.splice(
+        0..0,
+        ["-o".to_string(), format!("SetEnv={}", vars.join(" "))],
+    );
+    Ok(())
+}
+
+/// Tokens `args_template` entries may reference.
+pub const TEMPLATE_TOKENS: &[&str] = &[
+    "project_root",
+    "notepad_path",
+    "session_id",
+    "package_version",
+];
+
+/// Replaces the resolved `command`'s arguments with `args_template`, if set,
+/// substituting its tokens. `${notepad_path}` is the path the server receives
+/// as `NOTEPADPP_PATH`.
+pub fn apply_args_template(
+    command: &mut zed::Command,
+    settings: &ServerSettings,
+    platform: Platform,
+    session_id: &str,
+) -> zed::Result<()> {
+    let Some(template) = &settings.args_template else {
+        return Ok(());
+    };
+    // Only look Notepad++ up when the template asks for it.
+    let notepad_path = if template.iter().any(|arg| arg.contains("${notepad_path}")) {
+        notepad::server_path(settings, platform)?
+    } else {
+        None
+    };
+    let project_root = settings
+        .project_root()
+        .map(|root| root.to_string_lossy().into_owned());
+    let value = |token: &str| match token {
+        "project_root" => project_root.clone(),
+        "notepad_path" => notepad_path.clone(),
+        "session_id" => Some(session_id.to_string()),
+        "package_version" => settings.package_version.clone(),
+        _ => None,
+    };
+    command.args = template
+        .iter()
+        .map(|arg| sanitize_arg(&substitute_tokens(arg, value)?))
+        .collect::<Result<_, LaunchError>>()?;
+    Ok(())
+}
+
+/// Expands each `${token}` in `arg` through `value`. Unknown tokens, tokens
+/// without a value, and unterminated `${` are errors rather than being left
+/// in the argument.
+fn substitute_tokens(
+    arg: &str,
+    value: impl Fn(&str) -> Option<String>,
+) -> Result<String, LaunchError> {
+    let mut expanded = String::new();
+    let mut rest = arg;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(LaunchError::UnterminatedToken {
+                entry: arg.to_string(),
+            });
+        };
+        let token = &rest[start + 2..start + end];
+        if !TEMPLATE_TOKENS.contains(&token) {
+            return Err(LaunchError::UnknownToken {
+                entry: arg.to_string(),
+                token: token.to_string(),
+            });
+        }
+        let Some(value) = value(token) else {
+            // `${project_root}` comes from `working_directory`.
+            let setting = match token {
+                "project_root" => "working_directory",
+                token => token,
+            };
+            return Err(LaunchError::UnsetToken {
+                entry: arg.to_string(),
+                token: token.to_string(),
+                setting: setting.to_string(),
+            });
+        };
+        expanded.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Flags shared by every `uv run` and `uvx` invocation of the server.
+fn uv_flags(settings: &ServerSettings) -> Vec<String> {
+    let mut flags = Vec::new();
+    if settings.offline() {
+        flags.push("--offline".to_string());
+    }
+    if settings.python_mode == PythonMode::System {
+        flags.extend(["--python-preference".to_string(), "system".to_string()]);
+    }
+    flags
+}
+
+/// Returns the configured `working_directory`, canonicalized, after checking
+/// it is a directory. [`PROJECT_ROOT`] leaves the directory to Zed.
+fn working_directory(settings: &ServerSettings) -> Result<Option<String>, LaunchError> {
+    let Some(dir) = settings
+        .working_directory
+        .as_deref()
+        .filter(|dir| *dir != PROJECT_ROOT)
+    else {
+        return Ok(None);
+    };
+    // `ServerSettings::from_json` only accepts an absolute working directory,
+    // which is the project root relative paths resolve against.
+    let resolved = canonicalize_setting_path("working_directory", dir, Path::new(""))?;
+    if !resolved.is_dir() {
+        return Err(LaunchError::BadPath(format!(
+            "`working_directory` {dir:?} is not a directory"
+        )));
+    }
+    Ok(Some(resolved.to_string_lossy().into_owned()))
+}
+
+/// Arguments for running the server module with a Python interpreter.
+///
+/// `zed::Command` has no working-directory field, so when one is configured a
+/// short bootstrap changes into it before running the module.
+fn python_args(working_directory: Option<&str>) -> Result<Vec<String>, LaunchError> {
+    Ok(match working_directory {
+        Some(dir) => vec![
+            "-c".to_string(),
+            format!(
+                "import os, runpy, sys; os.chdir(sys.argv.pop(1)); \
+                 runpy.run_module({SERVER_MODULE:?}, run_name=\"__main__\", alter_sys=True)"
+            ),
+            sanitize_arg(dir)?,
+        ],
+        None => vec!["-m".to_string(), SERVER_MODULE.to_string()],
+    })
+}
+
+/// Bridges Zed's stdio transport to a server already listening at `url`
+/// through `mcp-proxy`, since extensions can only hand Zed a command to spawn.
+pub fn resolve_http_bridge(settings: &ServerSettings, url: &str) -> zed::Result<zed::Command> {
+    let mut args = Vec::new();
+    if settings.offline() {
+        args.push("--offline".to_string());
+    }
+    args.extend([
+        "mcp-proxy".to_string(),
+        "--transport".to_string(),
+        "streamablehttp".to_string(),
+        sanitize_arg(url)?,
+    ]);
+    Ok(command("uvx", args))
+}
+
+/// Runs the server from the local checkout at `dev_repo_path` through `uv`.
+pub fn resolve_dev_launcher(
+    settings: &ServerSettings,
+    uv_version: &mut Option<Version>,
+) -> zed::Result<zed::Command> {
+    let Some(repo_path) = &settings.dev_repo_path else {
+        return Err(LaunchError::NoDevRepo.into());
+    };
+    if settings.ssh_target.is_some() {
+        return Err(LaunchError::DevOverSsh.into());
+    }
+    // As for the released server, `uv` on Zed's PATH is assumed when the
+    // extension cannot check for it.
+    if host::is_available("uv") {
+        check_uv_version("uv", uv_version)?;
+    }
+
+    let mut args = vec!["run".to_string()];
+    args.extend(uv_flags(settings));
+    args.extend([
+        "--directory".to_string(),
+        sanitize_arg(repo_path)?,
+        "python".to_string(),
+        "-m".to_string(),
+        SERVER_MODULE.to_string(),
+    ]);
+    Ok(command("uv", args))
+}
+
+/// Wraps a stdio `command` in `py-spy record` when `trace` is enabled, writing
+/// the profile to `trace_output`. Without `py-spy` on Zed's `PATH` the command runs
+/// untraced and `NOTEPADPP_TRACE_UNAVAILABLE` tells the server why.
+pub fn with_tracer(command: zed::Command, settings: &ServerSettings) -> zed::Result<zed::Command> {
+    if !settings.trace() || settings.transport != Transport::Stdio || settings.ssh_target.is_some()
+    {
+        return Ok(command);
+    }
+    if !host::is_available("py-spy") {
+        let mut command = command;
+        command.env.push((
+            "NOTEPADPP_TRACE_UNAVAILABLE".to_string(),
+            "`trace` is enabled but py-spy is not on PATH".to_string(),
+        ));
+        return Ok(command);
+    };
+
+    // `uv run` starts the server as a child process, so follow subprocesses.
+    let mut args = vec![
+        "record".to_string(),
+        "--subprocesses".to_string(),
+        "--output".to_string(),
+        sanitize_arg(&settings.trace_output()?.to_string_lossy())?,
+        "--".to_string(),
+        command.command,
+    ];
+    args.extend(command.args);
+    Ok(zed::Command {
+        command: "py-spy".to_string(),
+        args,
+        env: command.env,
+    })
+}
+
+/// Checks that a settings-derived argument survives being passed to the
+/// server intact, rejecting NUL bytes anywhere and, when Zed runs on Windows,
+/// unbalanced double quotes that would split or merge arguments.
+pub fn sanitize_arg(arg: &str) -> Result<String, LaunchError> {
+    let (os, _) = zed::current_platform();
+    sanitize_arg_for(arg, os == zed::Os::Windows)
+}
+
+fn sanitize_arg_for(arg: &str, windows: bool) -> Result<String, LaunchError> {
+    if arg.contains('\0') {
+        return Err(LaunchError::BadArgument {
+            arg: arg.to_string(),
+            reason: "contains a NUL byte",
+        });
+    }
+    if windows {
+        // Windows argument parsing treats `\"` as a literal quote.
+        let mut quotes = 0;
+        let mut escaped = false;
+        for c in arg.chars() {
+            if c == '"' && !escaped {
+                quotes += 1;
+            }
+            escaped = c == '\\' && !escaped;
+        }
+        if quotes % 2 != 0 {
+            return Err(LaunchError::BadArgument {
+                arg: arg.to_string(),
+                reason: "has unbalanced double quotes",
+            });
+        }
+    }
+    Ok(arg.to_string())
+}
+
+/// Returns the version of `uv`, failing unless it is at least
+/// [`MIN_UV_VERSION`]. `uv --version` only runs when nothing is cached yet.
+pub fn check_uv_version(uv: &str, cached: &mut Option<Version>) -> Result<Version, LaunchError> {
+    let version = match *cached {
+        Some(version) => version,
+        None => {
+            let output = host::run(&zed::Command::new(uv).arg("--version")).map_err(|err| {
+                LaunchError::UvVersionUnknown(format!("failed to run `{uv} --version`: {err}"))
+            })?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = parse_uv_version(&stdout).ok_or_else(|| {
+                LaunchError::UvVersionUnknown(format!("unexpected output {stdout:?}"))
+            })?;
+            *cached = Some(version);
+            version
+        }
+    };
+
+    require_min_uv(version)
+}
+
+fn require_min_uv(version: Version) -> Result<Version, LaunchError> {
+    if version < MIN_UV_VERSION {
+        return Err(LaunchError::UvTooOld {
+            found: version.to_string(),
+        });
+    }
+    Ok(version)
+}
+
+/// Parses output such as `uv 0.4.18 (7b55e9790 2024-10-01)`.
+fn parse_uv_version(output: &str) -> Option<Version> {
+    let version = output.split_whitespace().nth(1)?;
+    let mut parts = version.splitn(3, '.').map(|part| {
+        let digits = part
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(part.len());
+        part[..digits].parse::<u64>().ok()
+    });
+    Some(Version {
+        major: parts.next()??,
+        minor: parts.next()??,
+        patch: parts.next()??,
+    })
+}
+
+/// Resolves `python_path` against the project root and confirms it names an
+/// executable file rather than a directory or a missing path.
+pub fn check_interpreter(
+    python_path: &str,
+    settings: &ServerSettings,
+) -> Result<PathBuf, LaunchError> {
+    let path = settings.resolve_path("python_path", python_path)?;
+    let metadata = path.metadata().map_err(|err| {
+        LaunchError::BadPath(format!(
+            "`python_path` {python_path:?} is not accessible: {err}"
+        ))
+    })?;
+    if metadata.is_dir() {
+        return Err(LaunchError::BadPath(format!(
+            "`python_path` {python_path:?} is a directory, expected a Python executable"
+        )));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err(LaunchError::BadPath(format!(
+                "`python_path` {python_path:?} is not executable"
+            )));
+        }
+    }
+
+    Ok(path)
+}
+
+fn command(program: impl Into<String>, args: impl IntoIterator<Item = String>) -> zed::Command {
+    zed::Command {
+        command: program.into(),
+        args: args.into_iter().collect(),
+        env: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    fn fake_command() -> zed::Command {
+        command("uv", ["run".to_string()])
+    }
+
+    #[test]
+    fn cache_reuses_command_for_identical_settings() {
+        let mut cache = LauncherCache::default();
+        let settings = ServerSettings::default();
+        let mut resolutions = 0;
+
+        for _ in 0..2 {
+            let command = cache
+                .get_or_resolve("notepadpp-mcp", &settings, |_| {
+                    resolutions += 1;
+                    Ok(fake_command())
+                })
+                .unwrap();
+            assert_eq!(command.command, "uv");
+        }
+
+        assert_eq!(resolutions, 1);
+    }
+
+    #[test]
+    fn cache_resolves_again_when_settings_change() {
+        let mut cache = LauncherCache::default();
+        let mut resolutions = 0;
+
+        let mut settings = ServerSettings::default();
+        cache
+            .get_or_resolve("notepadpp-mcp", &settings, |_| {
+                resolutions += 1;
+                Ok(fake_command())
+            })
+            .unwrap();
+
+        settings.package_version = Some("1.2.3".to_string());
+        cache
+            .get_or_resolve("notepadpp-mcp", &settings, |_| {
+                resolutions += 1;
+                Ok(fake_command())
+            })
+            .unwrap();
+
+        assert_eq!(resolutions, 2);
+    }
+
+    #[test]
+    fn fingerprint_ignores_env_order_but_not_values() {
+        let mut command = fake_command();
+        command.env = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ];
+        let mut reordered = command.clone();
+        reordered.env.reverse();
+        assert_eq!(config_fingerprint(&command), config_fingerprint(&reordered));
+
+        reordered.env[0].1 = "3".to_string();
+        assert_ne!(config_fingerprint(&command), config_fingerprint(&reordered));
+    }
+
+    #[test]
+    fn fingerprint_ignores_the_session_id() {
+        let command = fake_command();
+        let mut with_session = command.clone();
+        with_session
+            .env
+            .push((SESSION_ID_VAR.to_string(), "1a2b3c4d".to_string()));
+        assert_eq!(
+            config_fingerprint(&command),
+            config_fingerprint(&with_session)
+        );
+
+        let mut other_session = with_session.clone();
+        other_session.env[0].1 = "5e6f7a8b".to_string();
+        with_session
+            .args
+            .insert(0, format!("SetEnv=\"{SESSION_ID_VAR}=1a2b3c4d\""));
+        other_session
+            .args
+            .insert(0, format!("SetEnv=\"{SESSION_ID_VAR}=5e6f7a8b\""));
+        assert_eq!(
+            config_fingerprint(&with_session),
+            config_fingerprint(&other_session)
+        );
+    }
+
+    #[test]
+    fn accepts_paths_with_spaces() {
+        let path = r"C:\Program Files\notepadpp-mcp";
+        assert_eq!(sanitize_arg_for(path, true).unwrap(), path);
+        assert_eq!(sanitize_arg_for(path, false).unwrap(), path);
+    }
+
+    #[test]
+    fn rejects_unbalanced_quotes_on_windows() {
+        let arg = r#"C:\Users\me"\repo"#;
+        assert_eq!(
+            sanitize_arg_for(arg, true),
+            Err(LaunchError::BadArgument {
+                arg: arg.to_string(),
+                reason: "has unbalanced double quotes",
+            })
+        );
+        assert!(sanitize_arg_for(arg, false).is_ok());
+        assert!(sanitize_arg_for(r#"say "hi""#, true).is_ok());
+        assert!(sanitize_arg_for(r#"escaped \" quote"#, true).is_ok());
+    }
+
+    #[test]
+    fn rejects_nul_bytes() {
+        for windows in [true, false] {
+            assert_eq!(
+                sanitize_arg_for("repo\0path", windows),
+                Err(LaunchError::BadArgument {
+                    arg: "repo\0path".to_string(),
+                    reason: "contains a NUL byte",
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_uv_older_than_the_minimum() {
+        let old = Version {
+            major: 0,
+            minor: 3,
+            patch: 9,
+        };
+        assert_eq!(
+            require_min_uv(old),
+            Err(LaunchError::UvTooOld {
+                found: "0.3.9".to_string()
+            })
+        );
+        assert_eq!(require_min_uv(MIN_UV_VERSION), Ok(MIN_UV_VERSION));
+    }
+
+    #[test]
+    fn rejects_directories_as_interpreters() {
+        let dir = scratch_dir("interpreter");
+        let result = check_interpreter(&dir.to_string_lossy(), &ServerSettings::default());
+        assert!(
+            matches!(&result, Err(LaunchError::BadPath(details)) if details.contains("is a directory")),
+            "{result:?}"
+        );
+    }
+
+    /// Stands in for a host where only `programs` are on `PATH`.
+    fn available(programs: &'static [&'static str]) -> impl Fn(&str) -> bool {
+        move |program| programs.contains(&program)
+    }
+
+    #[test]
+    fn runs_uv_remotely_with_an_ssh_target() {
+        let settings = ServerSettings {
+            ssh_target: Some("me@build-box".to_string()),
+            with_packages: vec!["requests>=2.31".to_string()],
+            ..ServerSettings::default()
+        };
+
+        let command = resolve_launcher_in(&settings, &mut None, available(&["ssh"])).unwrap();
+        assert_eq!(command.command, "ssh");
+        assert_eq!(
+            command.args,
+            [
+                "-T",
+                "me@build-box",
+                "uv",
+                "run",
+                "--with",
+                "\"requests>=2.31\"",
+                "--with",
+                "notepadpp-mcp",
+                "python",
+                "-m",
+                SERVER_MODULE,
+            ]
+        );
+
+        let mut command = command;
+        command.env = vec![
+            ("NOTEPADPP_LOG_LEVEL".to_string(), "INFO".to_string()),
+            ("API_TOKEN".to_string(), "secret".to_string()),
+        ];
+        forward_env_over_ssh(&mut command, &settings).unwrap();
+        assert_eq!(
+            command.args[..3],
+            ["-o", "SetEnv=\"NOTEPADPP_LOG_LEVEL=INFO\"", "-T"]
+        );
+    }
+
+    #[test]
+    fn launches_locally_without_an_ssh_target() {
+        let settings = ServerSettings::default();
+
+        let mut command =
+            resolve_launcher_in(&settings, &mut None, available(&["ssh", "uvx"])).unwrap();
+        assert_eq!(command.command, "uvx");
+        assert_eq!(command.args, [PACKAGE_NAME]);
+
+        command.env = vec![("NOTEPADPP_LOG_LEVEL".to_string(), "INFO".to_string())];
+        forward_env_over_ssh(&mut command, &settings).unwrap();
+        assert_eq!(command.args, [PACKAGE_NAME]);
+    }
+
+    #[test]
+    fn refuses_to_drop_a_pinned_version() {
+        let settings = ServerSettings {
+            package_version: Some("1.2.3".to_string()),
+            ..ServerSettings::default()
+        };
+        let err = resolve_launcher_in(&settings, &mut None, available(&["python3"])).unwrap_err();
+        assert_eq!(
+            err,
+            LaunchError::PinUnsupported {
+                version: "1.2.3".to_string(),
+                launcher: "`python3`".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn refuses_to_drop_extra_packages() {
+        let settings = ServerSettings {
+            with_packages: vec!["requests>=2.31".to_string()],
+            ..ServerSettings::default()
+        };
+        let err = resolve_launcher_in(&settings, &mut None, available(&["python"])).unwrap_err();
+        assert_eq!(
+            err,
+            LaunchError::PackagesUnsupported {
+                packages: "requests>=2.31".to_string(),
+                launcher: "`python`".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn reports_when_no_launcher_is_found() {
+        let settings = ServerSettings::default();
+        let err = resolve_launcher_in(&settings, &mut None, available(&[])).unwrap_err();
+        assert_eq!(err, LaunchError::NoLauncher);
+        assert_eq!(
+            uv_launcher(&settings).unwrap().args,
+            ["run", "notepadpp_mcp.tools.server:run"]
+        );
+    }
+
+    fn token_value(token: &str) -> Option<String> {
+        match token {
+            "project_root" => Some("/work/project".to_string()),
+            "notepad_path" => Some(r"C:\Notepad++\notepad++.exe".to_string()),
+            "session_id" => Some("1a2b3c4d".to_string()),
+            "package_version" => Some("1.2.3".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn substitutes_each_token() {
+        for (arg, expected) in [
+            ("--root=${project_root}", "--root=/work/project"),
+            ("${notepad_path}", r"C:\Notepad++\notepad++.exe"),
+            ("--session ${session_id}", "--session 1a2b3c4d"),
+            ("notepadpp-mcp==${package_version}", "notepadpp-mcp==1.2.3"),
+            ("${session_id}-${session_id}", "1a2b3c4d-1a2b3c4d"),
+            ("no tokens", "no tokens"),
+        ] {
+            assert_eq!(substitute_tokens(arg, token_value).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_and_unset_tokens() {
+        let err = substitute_tokens("--cwd=${home}", token_value).unwrap_err();
+        assert_eq!(
+            err,
+            LaunchError::UnknownToken {
+                entry: "--cwd=${home}".to_string(),
+                token: "home".to_string(),
+            }
+        );
+
+        let err = substitute_tokens("${package_version}", |_| None).unwrap_err();
+        assert!(
+            err.to_string().contains("`package_version` is not set"),
+            "{err}"
+        );
+
+        let err = substitute_tokens("${project_root", token_value).unwrap_err();
+        assert!(
+            matches!(err, LaunchError::UnterminatedToken { .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn retries_until_resolution_succeeds() {
+        let mut calls = 0;
+        let command = with_retries(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(LaunchError::UvNotFound)
+            } else {
+                Ok(fake_command())
+            }
+        });
+
+        assert_eq!(command.unwrap().command, "uv");
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn gives_up_after_the_last_attempt() {
+        let mut calls = 0;
+        let result = with_retries(2, Duration::ZERO, || {
+            calls += 1;
+            Err(LaunchError::NoLauncher)
+        });
+
+        assert_eq!(result.unwrap_err(), LaunchError::NoLauncher);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn does_not_retry_configuration_errors() {
+        let mut calls = 0;
+        let result = with_retries(3, Duration::ZERO, || {
+            calls += 1;
+            Err(LaunchError::UvTooOld {
+                found: "0.3.9".to_string(),
+            })
+        });
+
+        assert!(matches!(result, Err(LaunchError::UvTooOld { .. })));
+        assert_eq!(calls, 1);
+    }
+}
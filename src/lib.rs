@@ -1,26 +1,243 @@
+mod configuration;
+mod doctor;
+mod environment;
+mod error;
+mod host;
+mod install;
+mod launcher;
+mod notepad;
+mod platform;
+mod probe;
+mod project_file;
+mod settings;
+mod slash_commands;
+#[cfg(test)]
+mod test_support;
+
+use std::sync::{Mutex, PoisonError};
+
+use error::LaunchError;
+use settings::ServerSettings;
+use zed::settings::ContextServerSettings;
 use zed_extension_api as zed;
 
-struct NotepadTextEditingExtension;
+struct NotepadTextEditingExtension {
+    /// `uv` version detected by the first launch, reused by later ones.
+    uv_version: Option<launcher::Version>,
+    /// Launcher commands resolved by earlier launches.
+    launchers: launcher::LauncherCache,
+    /// Recent result of checking whether the server package is installed,
+    /// shared with `/notepad-doctor`, which only gets `&self`.
+    installed: Mutex<install::InstalledCache>,
+    /// Settings used by the most recent server launch.
+    settings: Option<ServerSettings>,
+    /// Id forwarded to every launched server, stable for this instance.
+    session_id: String,
+    /// What the most recent server launch ran.
+    last_launch: Option<launcher::LaunchRecord>,
+}
 
 impl zed::Extension for NotepadTextEditingExtension {
     fn new() -> Self {
-        Self
+        Self {
+            uv_version: None,
+            launchers: launcher::LauncherCache::default(),
+            installed: Mutex::default(),
+            settings: None,
+            session_id: environment::new_session_id(),
+            last_launch: None,
+        }
     }
 
     fn context_server_command(
         &mut self,
         id: &zed::ContextServerId,
-        _project: &zed::Project,
+        project: &zed::Project,
     ) -> zed::Result<zed::Command> {
+        if !matches!(id.as_ref(), "notepadpp-mcp" | "notepadpp-mcp-dev") {
+            return Err(LaunchError::UnknownServer(id.as_ref().to_string()).into());
+        }
+        let state = LaunchState {
+            uv_version: &mut self.uv_version,
+            launchers: &mut self.launchers,
+            installed: self
+                .installed
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        };
+        let (command, settings, record) = launch_command(id, project, &self.session_id, state)?;
+        self.settings = Some(settings);
+        self.last_launch = Some(record);
+        Ok(command)
+    }
+
+    fn context_server_configuration(
+        &mut self,
+        id: &zed::ContextServerId,
+        _project: &zed::Project,
+    ) -> zed::Result<Option<zed::ContextServerConfiguration>> {
         match id.as_ref() {
-            "notepadpp-mcp" => Ok(zed::Command {
-                command: "uv".to_string(),
-                args: vec!["run".to_string(), "notepadpp_mcp.tools.server:run".to_string()],
-                env: Default::default(),
-            }),
-            _ => Err(format!("Unknown server: {}", id.as_ref())),
+            "notepadpp-mcp" | "notepadpp-mcp-dev" => {
+                configuration::context_server_configuration().map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn complete_slash_command_argument(
+        &self,
+        command: zed::SlashCommand,
+        args: Vec<String>,
+    ) -> zed::Result<Vec<zed::SlashCommandArgumentCompletion>> {
+        match command.name.as_str() {
+            "notepad-open" => slash_commands::complete_path(&args),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn run_slash_command(
+        &self,
+        command: zed::SlashCommand,
+        args: Vec<String>,
+        worktree: Option<&zed::Worktree>,
+    ) -> zed::Result<zed::SlashCommandOutput> {
+        let mut installed = self
+            .installed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match command.name.as_str() {
+            "notepad-open" => slash_commands::open(self.settings.as_ref(), &args, worktree),
+            "notepad-version" => slash_commands::version(self.settings.as_ref()),
+            "notepad-doctor" => slash_commands::doctor(
+                self.settings.as_ref(),
+                self.last_launch.as_ref(),
+                self.uv_version,
+                &self.session_id,
+                &mut installed,
+            ),
+            "notepad-restart" => slash_commands::restart(self.settings.as_ref()),
+            "notepad-command" => slash_commands::command(
+                self.last_launch.as_ref(),
+                self.uv_version,
+                &self.session_id,
+                &mut installed,
+            ),
+            name => Err(format!("Unknown slash command: {name}")),
         }
     }
 }
 
+/// Extension state a launch reads and updates.
+struct LaunchState<'a> {
+    uv_version: &'a mut Option<launcher::Version>,
+    launchers: &'a mut launcher::LauncherCache,
+    installed: &'a mut install::InstalledCache,
+}
+
+/// Reads the settings of `id` and builds its command through
+/// [`server_command`], installing and probing as needed. Returns the parsed
+/// settings and a record of the launch alongside the command.
+fn launch_command(
+    id: &zed::ContextServerId,
+    project: &zed::Project,
+    session_id: &str,
+    state: LaunchState,
+) -> zed::Result<(zed::Command, ServerSettings, launcher::LaunchRecord)> {
+    let settings = ContextServerSettings::for_project(id.as_ref(), project)?;
+    if ServerSettings::is_disabled(settings.settings.as_ref()) {
+        return Err(LaunchError::Disabled(id.as_ref().to_string()).into());
+    }
+    let raw_settings = settings.settings.clone();
+    let server_settings = ServerSettings::from_json(settings.settings)?;
+    if server_settings.requires_project_root() && project.worktree_ids().is_empty() {
+        return Err(LaunchError::NoProjectRoot(id.as_ref().to_string()).into());
+    }
+
+    let user_command = match settings.command {
+        Some(zed::settings::CommandSettings {
+            path: Some(path),
+            arguments,
+            env,
+        }) => Some(zed::Command {
+            command: path,
+            args: arguments
+                .unwrap_or_default()
+                .iter()
+                .map(|arg| launcher::sanitize_arg(arg))
+                .collect::<Result<_, LaunchError>>()?,
+            env: env.unwrap_or_default().into_iter().collect(),
+        }),
+        _ => None,
+    };
+
+    let command = server_command(
+        id.as_ref(),
+        &server_settings,
+        user_command.clone(),
+        session_id,
+        state,
+        true,
+        true,
+    )?;
+    let record = launcher::LaunchRecord {
+        id: id.as_ref().to_string(),
+        fingerprint: launcher::config_fingerprint(&command),
+        settings: raw_settings,
+        user_command,
+    };
+    Ok((command, server_settings, record))
+}
+
+/// Builds the command that starts server `id`: the user's own `command` block
+/// when given, otherwise the resolved launcher (or a cached result of it) with
+/// arguments, environment, and tracer applied. Launches and the
+/// `/notepad-command` and `/notepad-doctor` previews share this, differing
+/// only in whether it may `install` the package and `uv` directories and
+/// `probe` the server.
+fn server_command(
+    id: &str,
+    settings: &ServerSettings,
+    user_command: Option<zed::Command>,
+    session_id: &str,
+    state: LaunchState,
+    install: bool,
+    probe: bool,
+) -> zed::Result<zed::Command> {
+    // A user-supplied `command` block replaces the default launcher entirely.
+    if let Some(mut command) = user_command {
+        environment::add_session_id(&mut command.env, session_id);
+        return Ok(command);
+    }
+
+    let platform = platform::Platform::current();
+    notepad::check_platform(settings, platform)?;
+    notepad::check_install(settings, platform)?;
+    if install {
+        environment::create_uv_dirs(settings)?;
+    }
+
+    let LaunchState {
+        uv_version,
+        launchers,
+        installed,
+    } = state;
+    let mut command = launchers.get_or_resolve(id, settings, |settings| match id {
+        "notepadpp-mcp-dev" => launcher::resolve_dev_launcher(settings, uv_version),
+        _ => launcher::resolve_server(settings, uv_version),
+    })?;
+    // Whether and how the package is installed depends on the launcher, so it
+    // is only decided once that is known.
+    if install && id == "notepadpp-mcp" {
+        install::ensure_installed(settings, &mut command, installed)?;
+    }
+    launcher::apply_args_template(&mut command, settings, platform, session_id)?;
+    command.env = environment::build_env(settings, platform)?;
+    environment::add_session_id(&mut command.env, session_id);
+    launcher::forward_env_over_ssh(&mut command, settings)?;
+    if probe && settings.probe_on_launch() {
+        probe::probe_server(&command, settings)?;
+    }
+    launcher::with_tracer(command, settings)
+}
+
 zed::register_extension!(NotepadTextEditingExtension);
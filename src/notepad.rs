@@ -0,0 +1,180 @@
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use zed_extension_api as zed;
+
+use crate::error::LaunchError;
+use crate::platform::{translate_wsl_path, Platform};
+use crate::settings::{ServerSettings, Transport};
+
+/// Returns the configured `notepad_path` as the server will see it, after
+/// checking it exists. Under WSL a Windows-style path is rewritten to its
+/// `/mnt` mount.
+pub fn configured_path(
+    settings: &ServerSettings,
+    platform: Platform,
+) -> zed::Result<Option<String>> {
+    let Some(notepad_path) = &settings.notepad_path else {
+        return Ok(None);
+    };
+    let notepad_path = if platform.wsl && !notepad_path.starts_with('/') {
+        translate_wsl_path(notepad_path)
+    } else {
+        notepad_path.clone()
+    };
+    let resolved = settings.resolve_path("notepad_path", &notepad_path)?;
+    Ok(Some(resolved.to_string_lossy().into_owned()))
+}
+
+/// The Notepad++ path handed to the server as `NOTEPADPP_PATH`. A server run
+/// over SSH gets `notepad_path` unchecked, since it lives on the remote
+/// machine. Otherwise it is the checked [`configured_path`], or on Windows a
+/// discovered install, so the server can skip its own search.
+pub fn server_path(settings: &ServerSettings, platform: Platform) -> zed::Result<Option<String>> {
+    if settings.ssh_target.is_some() {
+        return Ok(settings.notepad_path.clone());
+    }
+    Ok(match configured_path(settings, platform)? {
+        Some(path) => Some(path),
+        None if platform.os == zed::Os::Windows => {
+            default_notepad_path().map(|path| path.to_string_lossy().into_owned())
+        }
+        None => None,
+    })
+}
+
+/// Default Notepad++ locations under the Windows program directories, followed
+/// by the per-user and Scoop installs when their base directories are known.
+pub fn standard_install_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = [
+        ("ProgramFiles", r"C:\Program Files"),
+        ("ProgramFiles(x86)", r"C:\Program Files (x86)"),
+    ]
+    .into_iter()
+    .map(|(variable, fallback)| {
+        let root = env::var(variable).unwrap_or_else(|_| fallback.to_string());
+        Path::new(&root).join("Notepad++").join("notepad++.exe")
+    })
+    .collect();
+    if let Ok(local) = env::var("LOCALAPPDATA") {
+        paths.push(Path::new(&local).join(r"Programs\Notepad++\notepad++.exe"));
+    }
+    if let Ok(profile) = env::var("USERPROFILE") {
+        paths.push(Path::new(&profile).join(r"scoop\apps\notepadplusplus\current\notepad++.exe"));
+    }
+    paths
+}
+
+/// Finds Notepad++ in the [standard install paths](standard_install_paths)
+/// or on `PATH`, returning the first hit.
+pub fn default_notepad_path() -> Option<PathBuf> {
+    default_notepad_path_in(
+        &standard_install_paths(),
+        &env::var_os("PATH").unwrap_or_default(),
+    )
+}
+
+fn default_notepad_path_in(candidates: &[PathBuf], path_var: &OsStr) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .or_else(|| find_on_path("notepad++", path_var))
+}
+
+/// Looks up `name` in each directory of `path_var`, also trying the `.exe`
+/// suffix so Windows installs resolve the same way.
+fn find_on_path(name: &str, path_var: &OsStr) -> Option<PathBuf> {
+    env::split_paths(path_var).find_map(|dir| {
+        [name.to_string(), format!("{name}.exe")]
+            .into_iter()
+            .map(|file_name| dir.join(file_name))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+/// Fails early on macOS and Linux (outside WSL) unless Wine, SSH, or a remote
+/// http server is configured, since Notepad++ only runs on Windows.
+pub fn check_platform(settings: &ServerSettings, platform: Platform) -> Result<(), LaunchError> {
+    let os = match platform.os {
+        zed::Os::Windows => return Ok(()),
+        zed::Os::Linux if platform.wsl => return Ok(()),
+        zed::Os::Mac => "macOS",
+        zed::Os::Linux => "Linux",
+    };
+    if settings.wine_prefix.is_some()
+        || settings.ssh_target.is_some()
+        || settings.transport == Transport::Http
+    {
+        return Ok(());
+    }
+    Err(LaunchError::UnsupportedPlatform { os })
+}
+
+/// Fails early on Windows when Notepad++ is neither configured through
+/// `notepad_path` nor installed in a standard location.
+///
+/// Wine and WSL installs live in places only the user knows, so the check is
+/// limited to native Windows and can be turned off with `check_notepad_install`.
+pub fn check_install(settings: &ServerSettings, platform: Platform) -> zed::Result<()> {
+    if !settings.check_notepad_install()
+        || settings.transport != Transport::Stdio
+        || settings.notepad_path.is_some()
+        || settings.ssh_target.is_some()
+        || platform.os != zed::Os::Windows
+    {
+        return Ok(());
+    }
+
+    if default_notepad_path().is_some() {
+        return Ok(());
+    }
+
+    Err(LaunchError::NotepadNotFound {
+        searched: standard_install_paths(),
+    }
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn prefers_the_first_existing_candidate() {
+        let dir = scratch_dir("candidates");
+        let installed = dir.join("notepad++.exe");
+        fs::write(&installed, "").unwrap();
+        let candidates = [dir.join("missing").join("notepad++.exe"), installed.clone()];
+
+        assert_eq!(
+            default_notepad_path_in(&candidates, OsStr::new("")),
+            Some(installed)
+        );
+    }
+
+    #[test]
+    fn falls_back_to_path() {
+        let dir = scratch_dir("path-search");
+        let on_path = dir.join("notepad++.exe");
+        fs::write(&on_path, "").unwrap();
+        let candidates = [dir.join("missing").join("notepad++.exe")];
+
+        assert_eq!(
+            default_notepad_path_in(&candidates, dir.as_os_str()),
+            Some(on_path)
+        );
+    }
+
+    #[test]
+    fn finds_nothing_without_an_install() {
+        let dir = scratch_dir("no-install");
+        let candidates = [dir.join("notepad++.exe")];
+
+        assert_eq!(default_notepad_path_in(&candidates, dir.as_os_str()), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+
+use zed_extension_api as zed;
+
+/// The machine the server is launched on.
+#[derive(Debug, Clone, Copy)]
+pub struct Platform {
+    pub os: zed::Os,
+    /// Whether this is Linux running under the Windows Subsystem for Linux,
+    /// where a Windows Notepad++ can be driven directly.
+    pub wsl: bool,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        let (os, _) = zed::current_platform();
+        Self {
+            os,
+            wsl: os == zed::Os::Linux && is_wsl(),
+        }
+    }
+}
+
+/// WSL kernels identify themselves with "microsoft" in `/proc/version`.
+fn is_wsl() -> bool {
+    fs::read_to_string("/proc/version")
+        .is_ok_and(|version| version.to_ascii_lowercase().contains("microsoft"))
+}
+
+/// Converts between Windows drive paths and their WSL mount points, e.g.
+/// `C:\Tools\notepad++.exe` and `/mnt/c/Tools/notepad++.exe`. Paths of
+/// neither form are returned unchanged.
+pub fn translate_wsl_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let drive = (bytes[0] as char).to_ascii_lowercase();
+        let rest = path[2..].replace('\\', "/");
+        let rest = rest.trim_start_matches('/');
+        return if rest.is_empty() {
+            format!("/mnt/{drive}")
+        } else {
+            format!("/mnt/{drive}/{rest}")
+        };
+    }
+
+    if let Some(rest) = path.strip_prefix("/mnt/") {
+        let mut chars = rest.chars();
+        if let Some(drive) = chars.next().filter(char::is_ascii_alphabetic) {
+            let rest = chars.as_str();
+            if rest.is_empty() || rest.starts_with('/') {
+                return format!(
+                    "{}:\\{}",
+                    drive.to_ascii_uppercase(),
+                    rest.trim_start_matches('/').replace('/', "\\")
+                );
+            }
+        }
+    }
+
+    path.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_windows_paths_to_wsl_mounts() {
+        assert_eq!(
+            translate_wsl_path(r"C:\Program Files\Notepad++\notepad++.exe"),
+            "/mnt/c/Program Files/Notepad++/notepad++.exe"
+        );
+        assert_eq!(translate_wsl_path(r"d:\"), "/mnt/d");
+    }
+
+    #[test]
+    fn translates_wsl_mounts_to_windows_paths() {
+        assert_eq!(
+            translate_wsl_path("/mnt/c/Program Files/Notepad++/notepad++.exe"),
+            r"C:\Program Files\Notepad++\notepad++.exe"
+        );
+    }
+
+    #[test]
+    fn leaves_other_paths_unchanged() {
+        assert_eq!(translate_wsl_path("/usr/bin/notepad"), "/usr/bin/notepad");
+        assert_eq!(translate_wsl_path("/mnt/data/file"), "/mnt/data/file");
+    }
+}
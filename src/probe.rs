@@ -0,0 +1,173 @@
+use std::time::Duration;
+
+use url::Url;
+use zed::http_client::{HttpMethod, HttpRequest, RedirectPolicy};
+use zed::serde_json::{self, Value};
+use zed_extension_api as zed;
+
+use crate::error::LaunchError;
+use crate::host;
+use crate::settings::ServerSettings;
+
+/// How long a probed server has to answer before it is considered hung.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// MCP `initialize` request sent to a stdio server to start the handshake.
+const INITIALIZE_REQUEST: &str = concat!(
+    r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"#,
+    r#""protocolVersion":"2024-11-05","capabilities":{},"#,
+    r#""clientInfo":{"name":"zed-notepadpp-probe","version":"0"}}}"#,
+);
+
+/// Spawns the probed server, sends it the request in `argv[2]`, and prints the
+/// first line it answers with. Exits with 3 when nothing arrives within
+/// `argv[1]` seconds. Zed can only run a command to completion, so the
+/// handshake and its timeout are driven from Python.
+const PROBE_SCRIPT: &str = "\
+import subprocess, sys, threading
+timeout, request = float(sys.argv[1]), sys.argv[2]
+server = subprocess.Popen(sys.argv[3:], stdin=subprocess.PIPE, stdout=subprocess.PIPE, stderr=subprocess.DEVNULL)
+try:
+    server.stdin.write(request.encode() + b'\\n')
+    server.stdin.flush()
+except OSError:
+    pass
+lines = []
+reader = threading.Thread(target=lambda: lines.append(server.stdout.readline()), daemon=True)
+reader.start()
+reader.join(timeout)
+server.kill()
+sys.stdout.write(lines[0].decode(errors='replace') if lines else '')
+sys.exit(0 if lines else 3)
+";
+
+/// Checks that the server started by `command` actually comes up: a stdio
+/// server must answer the MCP `initialize` request, and an http server's
+/// `/api/health` endpoint must respond successfully.
+pub fn probe_server(command: &zed::Command, settings: &ServerSettings) -> Result<(), LaunchError> {
+    match settings.http_url() {
+        Some(url) => probe_http(url),
+        None => probe_stdio(command, PROBE_TIMEOUT),
+    }
+}
+
+/// Starts `command` under [`PROBE_SCRIPT`], which sends it the `initialize`
+/// request and waits up to `timeout` for a JSON-RPC line on its stdout. The
+/// probed process is always killed afterwards; Zed spawns its own copy.
+fn probe_stdio(command: &zed::Command, timeout: Duration) -> Result<(), LaunchError> {
+    let Some(python) = ["python3", "python"]
+        .into_iter()
+        .find(|python| host::is_available(python))
+    else {
+        return Err(LaunchError::ProbeNeedsPython);
+    };
+    let probe = zed::Command::new(python)
+        .args([
+            "-c".to_string(),
+            PROBE_SCRIPT.to_string(),
+            timeout.as_secs_f64().to_string(),
+            INITIALIZE_REQUEST.to_string(),
+            command.command.clone(),
+        ])
+        .args(command.args.iter().cloned())
+        .envs(command.env.iter().cloned());
+    let program = command.command.clone();
+    let output = host::run(&probe).map_err(|details| LaunchError::ProbeFailed {
+        program: program.clone(),
+        details,
+    })?;
+    let line = String::from_utf8_lossy(&output.stdout);
+
+    match output.status {
+        Some(0) if is_handshake(&line) => Ok(()),
+        Some(0) if line.is_empty() => Err(LaunchError::ProbeExited { program }),
+        Some(0) => Err(LaunchError::ProbeUnexpectedOutput {
+            program,
+            output: line.trim_end().to_string(),
+        }),
+        Some(3) => Err(LaunchError::ProbeTimedOut {
+            program,
+            secs: timeout.as_secs(),
+        }),
+        _ => Err(LaunchError::ProbeFailed {
+            program,
+            details: String::from_utf8_lossy(&output.stderr)
+                .trim_end()
+                .to_string(),
+        }),
+    }
+}
+
+/// Whether `line` is a JSON-RPC response to the `initialize` request.
+fn is_handshake(line: &str) -> bool {
+    serde_json::from_str::<Value>(line).is_ok_and(|message| {
+        message.get("jsonrpc").and_then(Value::as_str) == Some("2.0")
+            && (message.get("result").is_some() || message.get("error").is_some())
+    })
+}
+
+/// Issues a GET to the `/api/health` endpoint on the host serving `url`.
+fn probe_http(url: &str) -> Result<(), LaunchError> {
+    let health = health_url(url)?;
+    HttpRequest::builder()
+        .method(HttpMethod::Get)
+        .url(health.as_str())
+        .redirect_policy(RedirectPolicy::FollowAll)
+        .build()
+        .and_then(|request| request.fetch())
+        .map_err(|details| LaunchError::HealthCheckFailed {
+            url: health.to_string(),
+            details,
+        })?;
+    Ok(())
+}
+
+fn health_url(url: &str) -> Result<Url, LaunchError> {
+    let mut health = Url::parse(url).map_err(|err| LaunchError::InvalidSetting {
+        setting: "url",
+        value: url.to_string(),
+        expected: err.to_string(),
+    })?;
+    health.set_path("/api/health");
+    health.set_query(None);
+    Ok(health)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn shell(script: &str) -> zed::Command {
+        zed::Command {
+            command: "sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string()],
+            env: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn accepts_servers_that_answer_the_handshake() {
+        let command =
+            shell(r#"read request; echo '{"jsonrpc":"2.0","id":1,"result":{"capabilities":{}}}'"#);
+        probe_stdio(&command, Duration::from_secs(10)).unwrap();
+    }
+
+    #[test]
+    fn reports_servers_that_never_answer() {
+        let command = shell("sleep 30");
+        let err = probe_stdio(&command, Duration::from_millis(200)).unwrap_err();
+        assert_eq!(
+            err,
+            LaunchError::ProbeTimedOut {
+                program: "sh".to_string(),
+                secs: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn health_endpoint_replaces_the_mcp_path() {
+        let health = health_url("http://127.0.0.1:10815/mcp?session=1").unwrap();
+        assert_eq!(health.as_str(), "http://127.0.0.1:10815/api/health");
+    }
+}
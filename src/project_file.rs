@@ -0,0 +1,93 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::LaunchError;
+use crate::settings::ServerSettings;
+
+/// Settings file teams can commit to a project, read from its root.
+pub const FILE_NAME: &str = ".notepadpp-mcp.toml";
+
+/// The subset of settings a project file may set, e.g.
+///
+/// ```toml
+/// package_version = "0.1.0"
+/// log_level = "DEBUG"
+/// enabled_tools = ["file", "tab", "text"]
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectFile {
+    pub package_version: Option<String>,
+    pub log_level: Option<String>,
+    pub notepad_path: Option<String>,
+    pub enabled_tools: Option<Vec<String>>,
+}
+
+impl ProjectFile {
+    /// Reads [`FILE_NAME`] from `root`, returning `None` when there is none.
+    pub fn load(root: &Path) -> Result<Option<Self>, LaunchError> {
+        let path = root.join(FILE_NAME);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                Self::parse(&text)
+                    .map(Some)
+                    .map_err(|err| LaunchError::InvalidProjectFile {
+                        path: path.display().to_string(),
+                        details: err.to_string(),
+                    })
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(LaunchError::BadPath(format!(
+                "Failed to read {}: {err}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Parses the file contents; errors point at the offending line.
+    fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Fills in the settings the user left unset, so Zed settings win over
+    /// the project file.
+    pub fn apply_to(self, settings: &mut ServerSettings) {
+        settings.package_version = settings.package_version.take().or(self.package_version);
+        settings.log_level = settings.log_level.take().or(self.log_level);
+        settings.notepad_path = settings.notepad_path.take().or(self.notepad_path);
+        settings.enabled_tools = settings.enabled_tools.take().or(self.enabled_tools);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_settings_override_the_project_file() {
+        let file = ProjectFile::parse(
+            "package_version = \"0.1.0\"\nlog_level = \"DEBUG\"\nenabled_tools = [\"file\"]\n",
+        )
+        .unwrap();
+        let mut settings = ServerSettings {
+            log_level: Some("ERROR".to_string()),
+            ..ServerSettings::default()
+        };
+        file.apply_to(&mut settings);
+
+        assert_eq!(settings.package_version.as_deref(), Some("0.1.0"));
+        assert_eq!(settings.log_level.as_deref(), Some("ERROR"));
+        assert_eq!(settings.enabled_tools, Some(vec!["file".to_string()]));
+        assert_eq!(settings.notepad_path, None);
+    }
+
+    #[test]
+    fn malformed_files_report_the_line() {
+        let err =
+            ProjectFile::parse("log_level = \"INFO\"\npackage_version = 0.1.0\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
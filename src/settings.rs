@@ -0,0 +1,852 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+use url::Url;
+use zed::serde_json::{self, Value};
+use zed_extension_api as zed;
+
+use crate::error::LaunchError;
+use crate::project_file::ProjectFile;
+
+/// Log levels understood by the server's `NOTEPADPP_LOG_LEVEL` variable.
+pub const LOG_LEVELS: &[&str] = &["DEBUG", "INFO", "WARNING", "ERROR"];
+
+/// Tool categories the server can enable through `NOTEPADPP_ENABLED_TOOLS`,
+/// one per `*_ops` tool module plus the agentic workflow tools.
+pub const TOOL_CATEGORIES: &[&str] = &[
+    "file", "tab", "text", "display", "status", "linting", "plugin", "session", "workflow",
+];
+
+/// `working_directory` value that keeps the server in the directory Zed starts
+/// it in, which requires a folder to be open.
+pub const PROJECT_ROOT: &str = "project_root";
+
+/// Every top-level key of the settings object the extension reads, including
+/// the `disabled` kill-switch checked before parsing.
+pub const KNOWN_SETTINGS: &[&str] = &[
+    "disabled",
+    "package_version",
+    "notepad_path",
+    "wine_prefix",
+    "auto_install",
+    "log_level",
+    "python_path",
+    "dev_repo_path",
+    "working_directory",
+    "transport",
+    "url",
+    "release_tag",
+    "env",
+    "check_notepad_install",
+    "enabled_tools",
+    "launch_retries",
+    "launch_retry_ms",
+    "uv_cache_dir",
+    "uv_data_dir",
+    "http_proxy",
+    "https_proxy",
+    "no_proxy",
+    "install_timeout_secs",
+    "instance_id",
+    "probe_on_launch",
+    "with_packages",
+    "install_check_ttl_secs",
+    "appearance",
+    "trace",
+    "trace_output",
+    "offline",
+    "python_mode",
+    "ssh_target",
+    "lock_file",
+    "args_template",
+    "allowed_roots",
+    "allow_external_roots",
+];
+
+/// How Zed talks to the MCP server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// Spawn the server and speak MCP over its stdin/stdout.
+    #[default]
+    Stdio,
+    /// Connect to an already running server at `url`.
+    Http,
+}
+
+/// Which Python interpreters `uv` may run the server with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PythonMode {
+    /// Let `uv` pick or download an interpreter for an isolated environment.
+    #[default]
+    Managed,
+    /// Prefer the interpreters already installed on the system.
+    System,
+}
+
+/// Light or dark mode for Notepad++ to match. Zed does not expose its
+/// appearance to extensions, so it is configured explicitly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Appearance {
+    Dark,
+    Light,
+    /// Leave the theme to Notepad++.
+    #[default]
+    Auto,
+}
+
+impl Appearance {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+            Self::Auto => "auto",
+        }
+    }
+}
+
+/// Extension-specific options from the `settings` object of the
+/// `notepadpp-mcp` context server entry in the Zed settings.
+#[derive(Debug, Default, Hash, Deserialize)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// Exact `notepadpp-mcp` package version to launch.
+    pub package_version: Option<String>,
+    /// Location of `notepad++.exe`, forwarded to the server as `NOTEPADPP_PATH`.
+    pub notepad_path: Option<String>,
+    /// Wine prefix holding the Notepad++ install on macOS and Linux.
+    pub wine_prefix: Option<String>,
+    /// Whether a missing server package is installed on launch. Defaults to `true`.
+    pub auto_install: Option<bool>,
+    /// Server log level, normalized to uppercase during parsing.
+    pub log_level: Option<String>,
+    /// Interpreter that runs the server directly, bypassing `uv`.
+    pub python_path: Option<String>,
+    /// Local checkout of the server used by the `notepadpp-mcp-dev` context server.
+    pub dev_repo_path: Option<String>,
+    /// Absolute directory the server is started in, or [`PROJECT_ROOT`].
+    pub working_directory: Option<String>,
+    /// Transport used to reach the server.
+    pub transport: Transport,
+    /// MCP endpoint of the server when `transport` is `http`.
+    pub url: Option<String>,
+    /// GitHub release whose wheel is installed instead of the PyPI package.
+    pub release_tag: Option<String>,
+    /// Extra variables for the server, overriding `.env` and derived values.
+    pub env: BTreeMap<String, String>,
+    /// Whether to fail early when Notepad++ is not installed. Defaults to `true`.
+    pub check_notepad_install: Option<bool>,
+    /// Tool categories to expose; all are enabled when unset.
+    pub enabled_tools: Option<Vec<String>>,
+    /// Attempts made to find a launcher on `PATH` before giving up. Defaults
+    /// to 3.
+    pub launch_retries: Option<u32>,
+    /// Base delay between launcher resolution attempts, growing linearly.
+    /// Defaults to 200 ms.
+    pub launch_retry_ms: Option<u64>,
+    /// Cache directory for `uv`, forwarded as `UV_CACHE_DIR`.
+    pub uv_cache_dir: Option<String>,
+    /// Data directory for `uv`, forwarded as `UV_DATA_DIR`.
+    pub uv_data_dir: Option<String>,
+    /// Proxy for plain HTTP requests made by `uv` and `pip`.
+    pub http_proxy: Option<String>,
+    /// Proxy for HTTPS requests made by `uv` and `pip`.
+    pub https_proxy: Option<String>,
+    /// Comma-separated hosts that bypass the proxies.
+    pub no_proxy: Option<String>,
+    /// Network timeout for automatic installs, passed to `uv` and `pip`. Not a
+    /// deadline for the whole install, which the extension cannot kill.
+    /// Defaults to 120.
+    pub install_timeout_secs: Option<u64>,
+    /// Notepad++ instance to attach to, forwarded as `NOTEPADPP_INSTANCE`. The
+    /// server attaches to the default instance when unset.
+    pub instance_id: Option<String>,
+    /// Whether to check that the server comes up before handing it to Zed.
+    /// Defaults to `false`, since probing spawns the server an extra time.
+    pub probe_on_launch: Option<bool>,
+    /// Extra Python requirements made available to `uv run` or `uvx` through
+    /// `--with`.
+    pub with_packages: Vec<String>,
+    /// Seconds a "package is installed" check stays valid. Defaults to 300.
+    pub install_check_ttl_secs: Option<u64>,
+    /// Theme mode forwarded as `NOTEPADPP_APPEARANCE`. Defaults to `auto`.
+    pub appearance: Option<Appearance>,
+    /// Whether to run the server under `py-spy record`. Defaults to `false`.
+    pub trace: Option<bool>,
+    /// Where the trace is written. Defaults to `notepadpp-mcp-trace.svg` in
+    /// `working_directory`, which must then be set.
+    pub trace_output: Option<String>,
+    /// Whether to forbid all network access: `uv` runs with `--offline` and
+    /// nothing is downloaded or installed. Defaults to `false`.
+    pub offline: Option<bool>,
+    /// Interpreter preference passed to `uv`.
+    pub python_mode: PythonMode,
+    /// `user@host` of a remote Windows machine to run the server on over SSH.
+    /// `notepad_path` then refers to the remote filesystem.
+    pub ssh_target: Option<String>,
+    /// Lock file the server leaves behind after a crash, removed by
+    /// `/notepad-restart`. Defaults to `notepadpp-mcp.lock` in
+    /// `working_directory`, which must then be set.
+    pub lock_file: Option<String>,
+    /// Arguments replacing the resolved launcher's defaults, with `${…}`
+    /// tokens substituted at launch.
+    pub args_template: Option<Vec<String>>,
+    /// Directories the server may access, forwarded as
+    /// `NOTEPADPP_ALLOWED_ROOTS`. Relative entries resolve against
+    /// `working_directory`. The server applies no restriction when empty.
+    pub allowed_roots: Vec<String>,
+    /// Whether `allowed_roots` may name directories outside the project.
+    /// Defaults to `false`.
+    pub allow_external_roots: Option<bool>,
+    /// Top-level keys outside [`KNOWN_SETTINGS`], ignored but reported by
+    /// `/notepad-doctor` since they are usually typos.
+    #[serde(skip)]
+    pub unknown_keys: Vec<String>,
+}
+
+impl ServerSettings {
+    /// Parses and validates the raw settings value; a missing or `null` value
+    /// yields the defaults. Keys left unset fall back to the project's
+    /// [`FILE_NAME`](crate::project_file::FILE_NAME), if it has one.
+    pub fn from_json(value: Option<Value>) -> zed::Result<Self> {
+        let mut settings: Self = match value.filter(|value| !value.is_null()) {
+            Some(value) => {
+                let unknown_keys = unknown_keys(&value);
+                let settings: Self = serde_json::from_value(value)
+                    .map_err(|err| LaunchError::MalformedSettings(err.to_string()))?;
+                Self {
+                    unknown_keys,
+                    ..settings
+                }
+            }
+            None => Self::default(),
+        };
+        // Relative paths resolve against `working_directory`, so it has nothing
+        // to resolve against itself. A remote one is checked by the remote shell.
+        if let (Some(root), None) = (settings.project_root(), &settings.ssh_target) {
+            if !root.is_absolute() {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "working_directory",
+                    value: root.display().to_string(),
+                    expected: format!("expected an absolute path or {PROJECT_ROOT:?}"),
+                }
+                .into());
+            }
+        }
+
+        // `zed::Project` does not expose worktree paths, so the file is looked
+        // up in `working_directory`, like `.env`.
+        if let Some(root) = settings.project_root() {
+            if let Some(project_file) = ProjectFile::load(&root)? {
+                project_file.apply_to(&mut settings);
+            }
+        }
+
+        if let Some(version) = &settings.package_version {
+            if !is_valid_version(version) {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "package_version",
+                    value: version.clone(),
+                    expected: "expected a version like \"1.2.3\"".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if let Some(level) = &mut settings.log_level {
+            let normalized = level.to_ascii_uppercase();
+            if !LOG_LEVELS.contains(&normalized.as_str()) {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "log_level",
+                    value: level.clone(),
+                    expected: format!("expected one of {}", LOG_LEVELS.join(", ")),
+                }
+                .into());
+            }
+            *level = normalized;
+        }
+
+        if let Some(tools) = &settings.enabled_tools {
+            let unknown: Vec<&str> = tools
+                .iter()
+                .map(String::as_str)
+                .filter(|tool| !TOOL_CATEGORIES.contains(tool))
+                .collect();
+            if !unknown.is_empty() {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "enabled_tools",
+                    value: unknown.join(", "),
+                    expected: format!("known categories are {}", TOOL_CATEGORIES.join(", ")),
+                }
+                .into());
+            }
+        }
+
+        for (setting, proxy) in [
+            ("http_proxy", &settings.http_proxy),
+            ("https_proxy", &settings.https_proxy),
+        ] {
+            if let Some(proxy) = proxy {
+                Url::parse(proxy).map_err(|err| LaunchError::InvalidSetting {
+                    setting,
+                    value: proxy.clone(),
+                    expected: err.to_string(),
+                })?;
+            }
+        }
+
+        let malformed: Vec<&str> = settings
+            .with_packages
+            .iter()
+            .map(String::as_str)
+            .filter(|requirement| !is_valid_requirement(requirement))
+            .collect();
+        if !malformed.is_empty() {
+            return Err(LaunchError::InvalidSetting {
+                setting: "with_packages",
+                value: malformed.join(", "),
+                expected: "expected requirements like \"requests\" or \"requests[socks]>=2.31,<3\""
+                    .to_string(),
+            }
+            .into());
+        }
+
+        if let Some(instance) = &settings.instance_id {
+            let is_token = !instance.is_empty()
+                && instance
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+            if !is_token {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "instance_id",
+                    value: instance.clone(),
+                    expected: "use only letters, digits, `-`, and `_`".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if let Some(target) = &settings.ssh_target {
+            if !is_ssh_target(target) {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "ssh_target",
+                    value: target.clone(),
+                    expected: "expected `user@host` using only letters, digits, `.`, `_`, and `-`"
+                        .to_string(),
+                }
+                .into());
+            }
+        }
+
+        for (setting, value) in [
+            ("launch_retries", settings.launch_retries.map(u64::from)),
+            ("install_timeout_secs", settings.install_timeout_secs),
+        ] {
+            if value == Some(0) {
+                return Err(LaunchError::InvalidSetting {
+                    setting,
+                    value: "0".to_string(),
+                    expected: "must be at least 1".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if let Some(tag) = &settings.release_tag {
+            // The tag names the download directory, so it must stay one component.
+            if tag.is_empty() || tag.starts_with('.') || tag.contains(['/', '\\']) {
+                return Err(LaunchError::InvalidSetting {
+                    setting: "release_tag",
+                    value: tag.clone(),
+                    expected: "expected a single path component such as \"v1.2.3\"".to_string(),
+                }
+                .into());
+            }
+        }
+
+        if settings.transport == Transport::Http {
+            let Some(url) = &settings.url else {
+                return Err(LaunchError::NoUrl.into());
+            };
+            let invalid_url = |expected: String| LaunchError::InvalidSetting {
+                setting: "url",
+                value: url.clone(),
+                expected,
+            };
+            let parsed = Url::parse(url).map_err(|err| invalid_url(err.to_string()))?;
+            if !matches!(parsed.scheme(), "http" | "https") {
+                return Err(invalid_url("expected an http or https URL".to_string()).into());
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Whether the raw settings set the `disabled` kill-switch. Checked before
+    /// parsing so a disabled server costs nothing to skip.
+    pub fn is_disabled(value: Option<&Value>) -> bool {
+        value
+            .and_then(|value| value.get("disabled"))
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    }
+
+    /// Hash of every setting, used to detect when cached launch state is stale.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether these settings only make sense with a folder open in Zed.
+    pub fn requires_project_root(&self) -> bool {
+        self.working_directory.as_deref() == Some(PROJECT_ROOT)
+    }
+
+    /// Directory treated as the project root for project-level files such as
+    /// `.env`. Zed does not expose worktree paths to extensions, so it is only
+    /// known when `working_directory` is set to an explicit path.
+    pub fn project_root(&self) -> Option<PathBuf> {
+        match self.working_directory.as_deref() {
+            Some(dir) if dir != PROJECT_ROOT => Some(PathBuf::from(dir)),
+            _ => None,
+        }
+    }
+
+    /// Resolves the path setting `setting` against [`Self::project_root`]. A
+    /// relative `path` is an error when there is no project root.
+    pub fn resolve_path(&self, setting: &str, path: &str) -> Result<PathBuf, LaunchError> {
+        match self.project_root() {
+            Some(root) => canonicalize_setting_path(setting, path, &root),
+            None if Path::new(path).is_absolute() => {
+                canonicalize_setting_path(setting, path, Path::new(""))
+            }
+            None => Err(LaunchError::NoWorkingDirectory(format!(
+                "The relative `{setting}` {path:?}"
+            ))),
+        }
+    }
+
+    /// The server's `url` when it is reached over http, which
+    /// [`Self::from_json`] guarantees is set for that transport.
+    pub fn http_url(&self) -> Option<&str> {
+        match self.transport {
+            Transport::Http => self.url.as_deref(),
+            Transport::Stdio => None,
+        }
+    }
+
+    pub fn auto_install(&self) -> bool {
+        self.auto_install.unwrap_or(true)
+    }
+
+    pub fn check_notepad_install(&self) -> bool {
+        self.check_notepad_install.unwrap_or(true)
+    }
+
+    pub fn probe_on_launch(&self) -> bool {
+        self.probe_on_launch.unwrap_or(false)
+    }
+
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
+    pub fn allow_external_roots(&self) -> bool {
+        self.allow_external_roots.unwrap_or(false)
+    }
+
+    /// `allowed_roots` for a server on the SSH host `target`, passed on as
+    /// written since they live on the remote filesystem.
+    pub fn remote_allowed_roots(&self, target: &str) -> Result<&[String], LaunchError> {
+        check_remote_roots(&self.allowed_roots, target, self.allow_external_roots())?;
+        Ok(&self.allowed_roots)
+    }
+
+    /// Canonicalizes `allowed_roots` against the project root. Without one,
+    /// entries cannot be checked against the project, so only absolute entries
+    /// with `allow_external_roots` are accepted.
+    pub fn allowed_roots(&self) -> Result<Vec<PathBuf>, LaunchError> {
+        match self.project_root() {
+            Some(root) => {
+                resolve_allowed_roots(&self.allowed_roots, &root, self.allow_external_roots())
+            }
+            None if self.allowed_roots.is_empty() => Ok(Vec::new()),
+            None if self.allow_external_roots() => self
+                .allowed_roots
+                .iter()
+                .map(|entry| self.resolve_path("allowed_roots", entry))
+                .collect(),
+            None => Err(LaunchError::NoWorkingDirectory(
+                "Keeping `allowed_roots` inside the project".to_string(),
+            )),
+        }
+    }
+
+    pub fn trace(&self) -> bool {
+        self.trace.unwrap_or(false)
+    }
+
+    pub fn trace_output(&self) -> Result<PathBuf, LaunchError> {
+        match (&self.trace_output, self.project_root()) {
+            (Some(path), _) => Ok(PathBuf::from(path)),
+            (None, Some(root)) => Ok(root.join("notepadpp-mcp-trace.svg")),
+            (None, None) => Err(LaunchError::NoWorkingDirectory(
+                "The default `trace_output`".to_string(),
+            )),
+        }
+    }
+
+    pub fn install_check_ttl(&self) -> Duration {
+        Duration::from_secs(self.install_check_ttl_secs.unwrap_or(300))
+    }
+
+    pub fn install_timeout(&self) -> Duration {
+        Duration::from_secs(self.install_timeout_secs.unwrap_or(120))
+    }
+
+    pub fn launch_retries(&self) -> u32 {
+        self.launch_retries.unwrap_or(3)
+    }
+
+    pub fn launch_retry_delay(&self) -> Duration {
+        Duration::from_millis(self.launch_retry_ms.unwrap_or(200))
+    }
+}
+
+/// Accepts `user@host`, rejecting anything ssh could read as an option or a
+/// remote shell could interpret.
+fn is_ssh_target(target: &str) -> bool {
+    let Some((user, host)) = target.split_once('@') else {
+        return false;
+    };
+    let is_part = |part: &str, extra: &[u8]| {
+        !part.is_empty()
+            && !part.starts_with('-')
+            && part
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || extra.contains(&b))
+    };
+    is_part(user, b"._-") && is_part(host, b".-")
+}
+
+/// Returns the top-level keys of `value` that are not in [`KNOWN_SETTINGS`].
+fn unknown_keys(value: &Value) -> Vec<String> {
+    value
+        .as_object()
+        .map(|object| {
+            object
+                .keys()
+                .filter(|key| !KNOWN_SETTINGS.contains(&key.as_str()))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Resolves the path setting `setting` against `root` when it is relative and
+/// canonicalizes it, so the result no longer depends on the directory the
+/// server starts in. The final component is kept as written, leaving a
+/// virtualenv's `bin/python` symlink intact.
+pub fn canonicalize_setting_path(
+    setting: &str,
+    path: &str,
+    root: &Path,
+) -> Result<PathBuf, LaunchError> {
+    let joined = root.join(path);
+    let resolved = match (joined.parent(), joined.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            fs::canonicalize(parent).map(|parent| parent.join(name))
+        }
+        _ => fs::canonicalize(&joined),
+    }
+    .and_then(|resolved| fs::metadata(&resolved).map(|_| resolved))
+    .map_err(|err| {
+        let relative = if Path::new(path).is_relative() {
+            format!(" (relative to {})", root.display())
+        } else {
+            String::new()
+        };
+        LaunchError::BadPath(format!(
+            "`{setting}` {path:?}{relative} could not be resolved: {err}"
+        ))
+    })?;
+
+    // Windows canonicalizes to `\\?\C:\...`, which not every program accepts.
+    Ok(
+        match resolved
+            .to_str()
+            .and_then(|path| path.strip_prefix(r"\\?\"))
+        {
+            Some(path) if path.as_bytes().get(1) == Some(&b':') => PathBuf::from(path),
+            _ => resolved,
+        },
+    )
+}
+
+/// Canonicalizes each of `roots` against `project`, rejecting missing entries,
+/// entries that are not directories, and, unless `allow_external`, entries
+/// outside `project` such as `../shared`.
+fn resolve_allowed_roots(
+    roots: &[String],
+    project: &Path,
+    allow_external: bool,
+) -> Result<Vec<PathBuf>, LaunchError> {
+    let canonical_project = fs::canonicalize(project).map_err(|err| {
+        LaunchError::BadPath(format!(
+            "The project root {} could not be resolved: {err}",
+            project.display()
+        ))
+    })?;
+
+    roots
+        .iter()
+        .map(|entry| {
+            let root = canonicalize_setting_path("allowed_roots", entry, project)?;
+            // Resolve a trailing symlink too, so a link in the project cannot
+            // point the server elsewhere.
+            let target = fs::canonicalize(&root).map_err(|err| {
+                LaunchError::BadPath(format!(
+                    "`allowed_roots` entry {entry:?} could not be resolved: {err}"
+                ))
+            })?;
+            if !target.is_dir() {
+                return Err(LaunchError::BadPath(format!(
+                    "`allowed_roots` entry {entry:?} is not a directory"
+                )));
+            }
+            if !allow_external && !target.starts_with(&canonical_project) {
+                return Err(LaunchError::ExternalRoot {
+                    entry: entry.clone(),
+                    project: project.display().to_string(),
+                });
+            }
+            Ok(root)
+        })
+        .collect()
+}
+
+/// Rejects remote `roots` that climb out of the project with `..`, unless
+/// `allow_external`. Remote paths cannot be resolved from here, so the check
+/// is lexical, splitting on both Unix and Windows separators.
+fn check_remote_roots(
+    roots: &[String],
+    target: &str,
+    allow_external: bool,
+) -> Result<(), LaunchError> {
+    if allow_external {
+        return Ok(());
+    }
+    match roots
+        .iter()
+        .find(|entry| entry.split(['/', '\\']).any(|part| part == ".."))
+    {
+        Some(entry) => Err(LaunchError::ExternalRoot {
+            entry: entry.clone(),
+            project: format!("on {target}"),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Accepts `MAJOR.MINOR.PATCH` with optional `-prerelease` and `+build` suffixes.
+fn is_valid_version(version: &str) -> bool {
+    let (version, build) = match version.split_once('+') {
+        Some((version, build)) => (version, Some(build)),
+        None => (version, None),
+    };
+    let (core, prerelease) = match version.split_once('-') {
+        Some((core, prerelease)) => (core, Some(prerelease)),
+        None => (version, None),
+    };
+
+    let is_numeric = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    let is_identifier = |part: &str| {
+        !part.is_empty()
+            && part.split('.').all(|id| {
+                !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            })
+    };
+
+    let parts: Vec<&str> = core.split('.').collect();
+    parts.len() == 3
+        && parts.iter().all(|part| is_numeric(part))
+        && prerelease.is_none_or(is_identifier)
+        && build.is_none_or(is_identifier)
+}
+
+/// Accepts a basic PEP 508 requirement: a project name, optional `[extras]`,
+/// and optional comma-separated version specifiers such as `>=1.0,<2`.
+fn is_valid_requirement(requirement: &str) -> bool {
+    let is_name = |name: &str| {
+        let bytes = name.as_bytes();
+        !bytes.is_empty()
+            && bytes[0].is_ascii_alphanumeric()
+            && bytes[bytes.len() - 1].is_ascii_alphanumeric()
+            && bytes
+                .iter()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'-'))
+    };
+
+    let requirement = requirement.trim();
+    let name_end = requirement
+        .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')))
+        .unwrap_or(requirement.len());
+    let (name, mut rest) = requirement.split_at(name_end);
+    if !is_name(name) {
+        return false;
+    }
+
+    if let Some(extras) = rest.strip_prefix('[') {
+        let Some((extras, after)) = extras.split_once(']') else {
+            return false;
+        };
+        if !extras.split(',').all(|extra| is_name(extra.trim())) {
+            return false;
+        }
+        rest = after;
+    }
+
+    let rest = rest.trim();
+    rest.is_empty()
+        || rest.split(',').all(|specifier| {
+            let specifier = specifier.trim();
+            let version = ["===", "==", "!=", "~=", ">=", "<=", ">", "<"]
+                .iter()
+                .find_map(|operator| specifier.strip_prefix(operator));
+            version.is_some_and(|version| {
+                let version = version.trim();
+                !version.is_empty()
+                    && version.bytes().all(|b| {
+                        b.is_ascii_alphanumeric()
+                            || matches!(b, b'.' | b'*' | b'+' | b'!' | b'-' | b'_')
+                    })
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn collects_unknown_keys_without_failing() {
+        let value = serde_json::json!({
+            "notepad_pth": "C:\\notepad++.exe",
+            "log_level": "debug",
+            "disabled": false,
+            "colour": "blue",
+        });
+        let settings = ServerSettings::from_json(Some(value)).unwrap();
+
+        assert_eq!(settings.unknown_keys, vec!["colour", "notepad_pth"]);
+        assert_eq!(settings.log_level.as_deref(), Some("DEBUG"));
+    }
+
+    #[test]
+    fn requires_an_absolute_working_directory() {
+        let value = serde_json::json!({ "working_directory": "project" });
+        let err = ServerSettings::from_json(Some(value)).unwrap_err();
+        assert_eq!(
+            err,
+            "Invalid `working_directory` \"project\": expected an absolute path or \"project_root\""
+        );
+
+        let value = serde_json::json!({ "working_directory": "project_root" });
+        assert!(ServerSettings::from_json(Some(value)).is_ok());
+    }
+
+    #[test]
+    fn resolves_relative_paths_against_the_project_root() {
+        let root = scratch_dir("root");
+        fs::create_dir_all(root.join("tools")).unwrap();
+        fs::write(root.join("tools").join("notepad++.exe"), "").unwrap();
+
+        let resolved =
+            canonicalize_setting_path("notepad_path", "tools/notepad++.exe", &root).unwrap();
+        assert!(resolved.is_absolute());
+        assert_eq!(
+            resolved,
+            fs::canonicalize(root.join("tools").join("notepad++.exe")).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_allowed_roots_that_escape_the_project() {
+        let base = scratch_dir("roots");
+        let project = base.join("project");
+        fs::create_dir_all(project.join("docs")).unwrap();
+        fs::create_dir_all(base.join("shared")).unwrap();
+
+        let roots = vec!["docs".to_string(), "../shared".to_string()];
+        let err = resolve_allowed_roots(&roots, &project, false).unwrap_err();
+        assert_eq!(
+            err,
+            LaunchError::ExternalRoot {
+                entry: "../shared".to_string(),
+                project: project.display().to_string(),
+            }
+        );
+
+        let resolved = resolve_allowed_roots(&roots, &project, true).unwrap();
+        assert_eq!(resolved[1], fs::canonicalize(base.join("shared")).unwrap());
+
+        // Remote roots cannot be resolved, but `..` is caught lexically.
+        let remote = vec![
+            r"C:\work\docs".to_string(),
+            r"docs\..\..\secrets".to_string(),
+        ];
+        assert_eq!(
+            check_remote_roots(&remote, "me@build-box", false),
+            Err(LaunchError::ExternalRoot {
+                entry: r"docs\..\..\secrets".to_string(),
+                project: "on me@build-box".to_string(),
+            })
+        );
+        assert_eq!(check_remote_roots(&remote, "me@build-box", true), Ok(()));
+    }
+
+    #[test]
+    fn names_the_setting_when_a_path_is_missing() {
+        let root = scratch_dir("missing-path");
+        let err = canonicalize_setting_path("python_path", "no/such/python", &root).unwrap_err();
+        let LaunchError::BadPath(details) = err else {
+            panic!("unexpected error {err:?}");
+        };
+        assert!(
+            details.starts_with("`python_path` \"no/such/python\" (relative to"),
+            "{details}"
+        );
+    }
+
+    #[test]
+    fn project_paths_require_a_working_directory() {
+        let settings = ServerSettings::default();
+        assert_eq!(settings.project_root(), None);
+        assert!(matches!(
+            settings.resolve_path("python_path", "venv/bin/python"),
+            Err(LaunchError::NoWorkingDirectory(_))
+        ));
+        assert!(matches!(
+            settings.trace_output(),
+            Err(LaunchError::NoWorkingDirectory(_))
+        ));
+
+        let settings = ServerSettings {
+            allowed_roots: vec!["src".to_string()],
+            ..ServerSettings::default()
+        };
+        assert!(matches!(
+            settings.allowed_roots(),
+            Err(LaunchError::NoWorkingDirectory(_))
+        ));
+    }
+}
@@ -0,0 +1,31 @@
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory under the system temp directory, removed again when
+/// dropped.
+pub struct ScratchDir(PathBuf);
+
+impl Deref for ScratchDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Creates an empty scratch directory unique to `name` and this test run.
+/// Tests run in parallel, so each needs its own `name`.
+pub fn scratch_dir(name: &str) -> ScratchDir {
+    let dir = env::temp_dir().join(format!("notepadpp-mcp-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    ScratchDir(dir)
+}